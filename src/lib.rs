@@ -3,7 +3,18 @@ mod tests;
 
 mod pattern;
 use pattern::FlatPatterns;
-pub use pattern::{ConstantConstructor, Constructor, Constructors, Pattern, SumtypeConstructor};
+pub use pattern::{
+    ConstantConstructor, Constructor, Constructors, InfiniteConstructor, LengthedConstructor,
+    Pattern, RecordError, SumtypeConstructor,
+};
+#[cfg(feature = "serde")]
+pub use pattern::{DeserializeConstructors, SerializeConstructors};
+
+mod printer;
+pub use printer::{DefaultPrinter, PatternPrinter, Printed};
 
 mod tree;
-pub use tree::{IsReachable, PatternTree};
+pub use tree::{
+    Access, Affixes, Arm, Decision, IsReachable, MergeError, MergeErrorKind, Missing, Node, Param,
+    PatternTree, Reachability,
+};
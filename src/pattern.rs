@@ -1,7 +1,5 @@
-use itertools::Itertools;
 use std::collections::VecDeque;
-use std::fmt;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::ops::RangeInclusive;
@@ -9,49 +7,213 @@ use std::ops::RangeInclusive;
 /// The constructors that can be used as patterns defined by you
 ///
 ///  * Lenghted
-///      are compared by their parameter count
-///      (such as slices)
+///    are compared by their parameter count
+///    (such as slices)
 ///
 ///  * Constant
-///      always matches with itself and must always have the same
-///      amount of parameters
+///    always matches with itself and must always have the same
+///    amount of parameters
 ///
 ///  * Variant
-///      sum type variants
+///    sum type variants
 ///
 ///  * Infinite
-///      have an infinite amount of variants, but can still be equal to each other
-///      (such as strings)
-///      
+///    have an infinite amount of variants, but can still be equal to each other
+///    (such as strings)
+///
+///  * Opaque
+///    named constants whose value can't be seen
+///    (such as `const` items)
+///    
 /// Even though PartialEq is only strictly required for `Infinite`, we use plenty of debug
 /// assertion to verify that your type checker didn't leave any holes which depends on PartialEq for
 /// the other associated types as well.
 pub trait Constructors: Clone + std::fmt::Debug {
-    type Lengthed: Clone + Debug + PartialEq;
+    type Lengthed: Clone + Debug + PartialEq + LengthedConstructor;
     type Constant: Clone + Debug + PartialEq + ConstantConstructor;
     type SumType: Clone + Debug + PartialEq + SumtypeConstructor;
-    type Infinite: Clone + Debug + PartialEq;
+    type Infinite: Clone + Debug + PartialEq + InfiniteConstructor;
+    /// Named constants matched by `Constructor::Opaque`
+    ///
+    /// Implementations from before opaque constants were added have to name a type here. Languages
+    /// without such constants can use `()` and never create `Constructor::Opaque` patterns.
+    type Opaque: Clone + Debug + PartialEq;
     type Wildcard: Clone + Debug + Default;
+    /// Data of your own attached to each pattern, such as its span in the source code
+    ///
+    /// It's handed back in the `Reachability` and `MergeError` of the patterns it's attached to.
+    /// Use `()` if you have nothing to attach, which is also what implementations from before
+    /// metadata was added need to keep working as they did.
+    type Meta: Clone + Debug + Default;
+}
+
+/// Constructors whose associated types can all be serialized, which is required to serialize
+/// patterns and pattern trees
+#[cfg(feature = "serde")]
+pub trait SerializeConstructors:
+    Constructors<
+    Lengthed: serde::Serialize,
+    Constant: serde::Serialize,
+    SumType: serde::Serialize,
+    Infinite: serde::Serialize,
+    Opaque: serde::Serialize,
+    Wildcard: serde::Serialize,
+    Meta: serde::Serialize,
+>
+{
+}
+
+#[cfg(feature = "serde")]
+impl<C> SerializeConstructors for C where
+    C: Constructors<
+        Lengthed: serde::Serialize,
+        Constant: serde::Serialize,
+        SumType: serde::Serialize,
+        Infinite: serde::Serialize,
+        Opaque: serde::Serialize,
+        Wildcard: serde::Serialize,
+        Meta: serde::Serialize,
+    >
+{
+}
+
+/// Constructors whose associated types can all be deserialized, which is required to deserialize
+/// patterns and pattern trees
+#[cfg(feature = "serde")]
+pub trait DeserializeConstructors<'de>:
+    Constructors<
+    Lengthed: serde::Deserialize<'de>,
+    Constant: serde::Deserialize<'de>,
+    SumType: serde::Deserialize<'de>,
+    Infinite: serde::Deserialize<'de>,
+    Opaque: serde::Deserialize<'de>,
+    Wildcard: serde::Deserialize<'de>,
+    Meta: serde::Deserialize<'de>,
+>
+{
+}
+
+#[cfg(feature = "serde")]
+impl<'de, C> DeserializeConstructors<'de> for C where
+    C: Constructors<
+        Lengthed: serde::Deserialize<'de>,
+        Constant: serde::Deserialize<'de>,
+        SumType: serde::Deserialize<'de>,
+        Infinite: serde::Deserialize<'de>,
+        Opaque: serde::Deserialize<'de>,
+        Wildcard: serde::Deserialize<'de>,
+        Meta: serde::Deserialize<'de>,
+    >
+{
 }
 
 pub trait SumtypeConstructor {
     fn max(&self) -> u64;
     fn params_for(&self, tag: u64) -> usize;
+
+    /// Whether the sum type may have variants besides the ones up to `max()`, such as when it's
+    /// defined outside of the program or may get more variants in later versions.
+    ///
+    /// Matching on a non-exhaustive sum type requires a wildcard even if every known variant
+    /// is covered.
+    fn is_non_exhaustive(&self) -> bool {
+        false
+    }
+
+    /// Whether the sum type has no variants at all, in which case `max()` is never called
+    fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Whether values of the variant can exist, which they can't if one of its parameters is of
+    /// an uninhabited type.
+    ///
+    /// Uninhabited variants are never required for a match to be exhaustive, and patterns
+    /// matching them are unreachable.
+    fn is_inhabited(&self, tag: u64) -> bool {
+        let _ = tag;
+        true
+    }
+}
+
+/// Infinite constructors which can opt into being matched by what they start or end with, such as
+/// strings matched by `"http://" ++ rest`.
+///
+/// Types without affixes can implement this trait without any methods. By default a value only
+/// starts and ends with itself, so `Constructor::InfiniteAffix` patterns would only match their
+/// affixes exactly.
+pub trait InfiniteConstructor: PartialEq {
+    fn starts_with(&self, prefix: &Self) -> bool {
+        self == prefix
+    }
+
+    fn ends_with(&self, suffix: &Self) -> bool {
+        self == suffix
+    }
+}
+
+pub trait LengthedConstructor {
+    /// The largest amount of parameters its values can have, such as for an array whose length
+    /// is statically known to be at most some size.
+    ///
+    /// Matching on an unbounded lengthed value is only exhaustive once every length is covered
+    /// by a rest pattern, a length range or a wildcard.
+    fn max_length(&self) -> Option<usize> {
+        None
+    }
 }
 
 pub trait ConstantConstructor {
     fn len_requirement(&self) -> usize;
+
+    /// The name of a parameter of a record, used to create patterns by naming a subset of its
+    /// fields and to print them by name.
+    fn field_name(&self, field: usize) -> Option<&str> {
+        let _ = field;
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "C: crate::SerializeConstructors",
+        deserialize = "C: crate::DeserializeConstructors<'de>"
+    ))
+)]
 pub struct Pattern<C: Constructors> {
     pub constr: Constructor<C>,
     pub params: Vec<Self>,
+    /// Set with `with_meta`, and otherwise left as `C::Meta::default()` by `Pattern::new`.
+    /// Patterns built as struct literals can move to `new` and `with_params` to not fill it in.
+    pub meta: C::Meta,
+}
+
+/// Patterns are compared by what they match, so their metadata is ignored
+impl<C: Constructors> PartialEq for Pattern<C>
+where
+    Constructor<C>: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.constr == other.constr && self.params == other.params
+    }
 }
 
 impl<C: Constructors> Pattern<C> {
     pub fn new(constr: Constructor<C>) -> Self {
-        Pattern { constr, params: vec![] }
+        Pattern {
+            constr,
+            params: vec![],
+            meta: C::Meta::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_meta(mut self, meta: C::Meta) -> Self {
+        self.meta = meta;
+        self
     }
 
     #[must_use]
@@ -72,6 +234,38 @@ impl<C: Constructors> Pattern<C> {
                     type_.params_for(*tag),
                     "sum type takes the wrong amount of parameters"
                 ),
+                Constructor::Lenghted(constr) => assert!(
+                    params.len() <= constr.max_length().unwrap_or(usize::MAX),
+                    "lengthed value has more parameters than its maximum length"
+                ),
+                Constructor::LengthedRest { constr, rest_at } => {
+                    assert!(
+                        *rest_at <= params.len(),
+                        "rest is placed after the last parameter"
+                    );
+                    assert!(
+                        params.len() <= constr.max_length().unwrap_or(usize::MAX),
+                        "lengthed value has more parameters than its maximum length"
+                    );
+                }
+                Constructor::LengthRange {
+                    constr,
+                    lengths,
+                    rest_at,
+                } => {
+                    assert!(
+                        *rest_at <= params.len(),
+                        "rest is placed after the last parameter"
+                    );
+                    assert!(
+                        params.len() <= *lengths.start(),
+                        "length range is shorter than its parameters"
+                    );
+                    assert!(
+                        *lengths.start() <= constr.max_length().unwrap_or(usize::MAX),
+                        "length range starts after the maximum length"
+                    );
+                }
                 _ => {}
             }
         }
@@ -84,11 +278,74 @@ impl<C: Constructors> Pattern<C> {
         Pattern {
             constr: Constructor::Wildcard(wc),
             params: vec![],
+            meta: C::Meta::default(),
+        }
+    }
+
+    /// A pattern for a record which names a subset of its fields, like `{ x: 0, .. }`
+    ///
+    /// The fields which aren't mentioned are filled with wildcards.
+    pub fn record(constr: C::Constant, fields: Vec<(&str, Self)>) -> Result<Self, RecordError> {
+        let mut params = vec![None; constr.len_requirement()];
+
+        for (name, pattern) in fields {
+            let field = (0..params.len())
+                .find(|&field| constr.field_name(field) == Some(name))
+                .ok_or_else(|| RecordError::UnknownField(name.to_string()))?;
+            if params[field].is_some() {
+                return Err(RecordError::DuplicateField(name.to_string()));
+            }
+            params[field] = Some(pattern);
+        }
+
+        let params = params
+            .into_iter()
+            .map(|param| param.unwrap_or_else(|| Pattern::wildcard(C::Wildcard::default())))
+            .collect();
+
+        Ok(Pattern::new(Constructor::Constant(constr)).with_params(params))
+    }
+
+    /// A pattern which matches if any of the alternatives match
+    pub fn or(alternatives: Vec<Self>) -> Self {
+        assert!(!alternatives.is_empty(), "or-pattern without alternatives");
+        Pattern {
+            constr: Constructor::Or,
+            params: alternatives,
+            meta: C::Meta::default(),
         }
     }
 }
 
+/// A field given to `Pattern::record` which doesn't line up with the fields of the record
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecordError {
+    /// The record has no field with this name
+    UnknownField(String),
+    /// The field was given more than once
+    DuplicateField(String),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::UnknownField(name) => write!(f, "record has no field named {}", name),
+            RecordError::DuplicateField(name) => write!(f, "field {} mentioned twice", name),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "C: crate::SerializeConstructors",
+        deserialize = "C: crate::DeserializeConstructors<'de>"
+    ))
+)]
 pub enum Constructor<C: Constructors> {
     SignedInteger {
         range: RangeInclusive<i128>,
@@ -98,19 +355,83 @@ pub enum Constructor<C: Constructors> {
         range: RangeInclusive<u128>,
         bitsize: u8,
     },
+    /// A range of Unicode scalar values, which leaves out the surrogates
+    Char {
+        range: RangeInclusive<char>,
+    },
+    /// A range of floats ordered from negative to positive infinity, where `-0.0` and `0.0` are
+    /// the same value.
+    ///
+    /// NaN isn't part of any other range and is only matched by a wildcard or by the range
+    /// `NaN..=NaN`, so matching on floats without a wildcard needs `NaN..=NaN` to be exhaustive.
+    Float {
+        range: RangeInclusive<f64>,
+    },
     Variant {
         type_: C::SumType,
         tag: u64,
     },
     Infinite(C::Infinite),
+    /// An infinite value starting with `prefix` and ending with `suffix`, such as `"http://" ++ _`
+    ///
+    /// The affixes are checked with the methods of `InfiniteConstructor`. When both are given
+    /// they're allowed to overlap, so unlike `"ab" ++ _ ++ "b"` this also matches `"ab"`.
+    InfiniteAffix {
+        prefix: Option<C::Infinite>,
+        suffix: Option<C::Infinite>,
+    },
     Lenghted(C::Lengthed),
+    /// A lengthed value with at least as many parameters as given
+    ///
+    /// The parameters before `rest_at` are matched from the start of the value and the ones
+    /// after it from the end, like `[first, .., last]`.
+    LengthedRest {
+        constr: C::Lengthed,
+        rest_at: usize,
+    },
+    /// A lengthed value whose amount of parameters is within the range, such as `len 1..=3`
+    ///
+    /// Unbounded ranges like `len 4..` end at `usize::MAX`. The given parameters are placed
+    /// around a rest at `rest_at` like with `LengthedRest`, so there can't be more of them than
+    /// the shortest length. Usually there are none, leaving every parameter unmatched.
+    LengthRange {
+        constr: C::Lengthed,
+        lengths: RangeInclusive<usize>,
+        rest_at: usize,
+    },
     Constant(C::Constant),
+    /// A named constant whose value can't be seen, such as a `const` item
+    ///
+    /// It's reachable unless a wildcard was included before it, but since it could be equal to
+    /// anything it never counts as covering any values.
+    Opaque(C::Opaque),
     Wildcard(C::Wildcard),
+    /// Matches if any of its parameters match
+    ///
+    /// Or-patterns are expanded into all of their combinations before being included into a
+    /// `PatternTree`, so this constructor never shows up in the tree itself. The amount of
+    /// combinations is the product of the amounts of alternatives, so a tuple of `n` two-way
+    /// or-patterns is included `2^n` times.
+    Or,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "C: crate::SerializeConstructors",
+        deserialize = "C: crate::DeserializeConstructors<'de>"
+    ))
+)]
 pub struct FlatPatterns<C: Constructors> {
     buf: VecDeque<(Constructor<C>, usize)>,
+    // the metadata of each of the original patterns, in their flattened order
+    metas: Vec<C::Meta>,
+    origin: usize,
+    // for each wildcard inserted in between the original patterns, the amount of patterns after it
+    inserted: Vec<usize>,
+    arm: usize,
 }
 
 impl<C: Constructors> Deref for FlatPatterns<C> {
@@ -130,15 +451,237 @@ impl<C: Constructors> Pattern<C> {
     pub fn flatten(&self) -> FlatPatterns<C> {
         let mut flat = FlatPatterns {
             buf: VecDeque::with_capacity(self.params.len() + 1),
+            metas: Vec::with_capacity(self.params.len() + 1),
+            origin: 0,
+            inserted: vec![],
+            arm: 0,
         };
         flat.include(self);
+        flat.origin = flat.len();
         flat
     }
+
+    /// The parameter indices leading to the n:th constructor of the flattened pattern
+    pub(crate) fn path_to(&self, mut n: usize) -> Vec<usize> {
+        let mut path = vec![];
+        let mut p = self;
+
+        while n != 0 {
+            n -= 1;
+            for (i, param) in p.params.iter().enumerate() {
+                let size = param.size();
+                if n < size {
+                    path.push(i);
+                    p = param;
+                    break;
+                }
+                n -= size;
+            }
+        }
+
+        path
+    }
+
+    /// The pattern at the end of the parameter indices
+    pub(crate) fn at(&self, path: &[usize]) -> Option<&Self> {
+        match path {
+            [] => Some(self),
+            [i, path @ ..] => self.params.get(*i)?.at(path),
+        }
+    }
+
+    // amount of constructors in the flattened pattern
+    fn size(&self) -> usize {
+        1 + self.params.iter().map(Self::size).sum::<usize>()
+    }
+}
+
+/// An or-free version of a pattern together with the paths to the alternatives it picked
+///
+/// Opaque constants are replaced by wildcards, in which case the expansion only has its
+/// reachability checked.
+pub(crate) struct Expansion<C: Constructors> {
+    pub(crate) pattern: Pattern<C>,
+    pub(crate) picked: Vec<Vec<usize>>,
+    pub(crate) opaque: bool,
+}
+
+impl<C: Constructors> Pattern<C> {
+    /// Every combination of alternatives of the or-patterns in this pattern
+    pub(crate) fn expand_alternatives(&self) -> Vec<Expansion<C>> {
+        self.expand(&mut vec![])
+    }
+
+    fn expand(&self, path: &mut Vec<usize>) -> Vec<Expansion<C>> {
+        if let Constructor::Or = self.constr {
+            let mut expansions = vec![];
+            for (i, alt) in self.params.iter().enumerate() {
+                path.push(i);
+                for mut expansion in alt.expand(path) {
+                    expansion.picked.push(path.clone());
+                    expansions.push(expansion);
+                }
+                path.pop();
+            }
+            return expansions;
+        }
+
+        if let Constructor::Opaque(_) = self.constr {
+            return vec![Expansion {
+                pattern: Pattern::wildcard(C::Wildcard::default()).with_meta(self.meta.clone()),
+                picked: vec![],
+                opaque: true,
+            }];
+        }
+
+        let mut expansions = vec![Expansion {
+            pattern: Pattern::new(self.constr.clone()).with_meta(self.meta.clone()),
+            picked: vec![],
+            opaque: false,
+        }];
+
+        for (i, param) in self.params.iter().enumerate() {
+            path.push(i);
+            let params = param.expand(path);
+            path.pop();
+
+            expansions = expansions
+                .into_iter()
+                .flat_map(|expansion| {
+                    params.iter().map(move |param| {
+                        let mut expansion = Expansion {
+                            pattern: expansion.pattern.clone(),
+                            picked: expansion.picked.clone(),
+                            opaque: expansion.opaque || param.opaque,
+                        };
+                        expansion.pattern.params.push(param.pattern.clone());
+                        expansion.picked.extend(param.picked.iter().cloned());
+                        expansion
+                    })
+                })
+                .collect();
+        }
+
+        expansions
+    }
+
+    // translates a path into one of our expansions into a path into this pattern
+    pub(crate) fn original_path(&self, path: &[usize], picked: &[Vec<usize>]) -> Vec<usize> {
+        let mut original = vec![];
+        let mut p = self;
+        let mut path = path.iter();
+
+        loop {
+            if let Constructor::Or = p.constr {
+                let i = (0..p.params.len())
+                    .find(|i| {
+                        original.push(*i);
+                        let found = picked.contains(&original);
+                        original.pop();
+                        found
+                    })
+                    .expect("expansion did not pick an alternative");
+                original.push(i);
+                p = &p.params[i];
+                continue;
+            }
+
+            match path.next() {
+                Some(&i) => {
+                    original.push(i);
+                    p = &p.params[i];
+                }
+                None => break original,
+            }
+        }
+    }
 }
 
 impl<C: Constructors> FlatPatterns<C> {
+    pub(crate) fn new() -> Self {
+        FlatPatterns {
+            buf: VecDeque::new(),
+            metas: vec![],
+            origin: 0,
+            inserted: vec![],
+            arm: 0,
+        }
+    }
+
+    #[must_use]
+    pub(crate) fn with_arm(mut self, arm: usize) -> Self {
+        self.arm = arm;
+        self
+    }
+
+    // drops the next `n` patterns including their parameters
+    pub(crate) fn skip_patterns(&mut self, n: usize) {
+        for _ in 0..n {
+            if let Some((_, params)) = self.pop_front() {
+                self.skip_patterns(params);
+            }
+        }
+    }
+
+    // inserts `n` wildcards after the next `at` patterns
+    pub(crate) fn insert_wildcards(&mut self, at: usize, n: usize) {
+        let mut i = 0;
+        for _ in 0..at {
+            let mut remaining = 1;
+            while remaining != 0 {
+                remaining += self[i].1;
+                remaining -= 1;
+                i += 1;
+            }
+        }
+        let after = self.len() - i;
+        for inserted in &mut self.inserted {
+            if *inserted >= after {
+                *inserted += n;
+            }
+        }
+        for _ in 0..n {
+            self.insert(i, (Constructor::Wildcard(C::Wildcard::default()), 0));
+            self.inserted.push(after);
+        }
+    }
+
+    // pops the next pattern including its parameters
+    pub(crate) fn pop_pattern(&mut self) -> Vec<(Constructor<C>, usize)> {
+        let mut pattern = vec![];
+        let mut remaining = 1;
+        while remaining != 0 {
+            let flat = self.pop_front().expect("FlatPatterns ended unexpectedly");
+            remaining += flat.1;
+            remaining -= 1;
+            pattern.push(flat);
+        }
+        pattern
+    }
+
+    // the arm this pattern is included as
+    pub(crate) fn arm(&self) -> usize {
+        self.arm
+    }
+
+    // the metadata of the n:th pattern of the original flattened pattern
+    pub(crate) fn meta(&self, n: usize) -> C::Meta {
+        self.metas.get(n).cloned().unwrap_or_default()
+    }
+
+    // the amount of patterns of the original flattened pattern which have been taken from it
+    pub(crate) fn consumed(&self) -> usize {
+        let inserted = self
+            .inserted
+            .iter()
+            .filter(|&&after| after < self.len())
+            .count();
+        self.origin.saturating_sub(self.len() - inserted)
+    }
+
     fn include(&mut self, p: &Pattern<C>) {
         self.push_back((p.constr.clone(), p.params.len()));
+        self.metas.push(p.meta.clone());
         p.params.iter().for_each(|p| self.include(p))
     }
 }
@@ -150,24 +693,3 @@ impl<C: Constructors> Iterator for FlatPatterns<C> {
         self.buf.pop_front()
     }
 }
-
-impl<S: fmt::Display, C: Constructors<SumType = S>> fmt::Display for Pattern<C> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self.params[..] {
-            [] => fmt::Display::fmt(&self.constr, f),
-            params => write!(f, "{} {}", self.constr, params.iter().format(" ")),
-        }
-    }
-}
-
-impl<S: fmt::Display, C: Constructors<SumType = S>> fmt::Display for Constructor<C> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Constructor::Variant { type_, tag } => {
-                write!(f, "{}[{}]", type_, tag)
-            }
-            Constructor::Wildcard(wc) => write!(f, "{:?}", wc),
-            _ => todo!(),
-        }
-    }
-}
@@ -0,0 +1,306 @@
+use crate::{ConstantConstructor, Constructor, Constructors, Pattern};
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// Names the constructors of patterns when printing them, such as in the syntax of your language
+///
+/// Nested patterns are parenthesized by the printer itself, so each method only has to write a
+/// single name or literal. Every method has a default, which is what `DefaultPrinter` uses.
+pub trait PatternPrinter<C: Constructors> {
+    fn variant(&self, f: &mut fmt::Formatter, type_: &C::SumType, tag: u64) -> fmt::Result {
+        write!(f, "{:?}[{}]", type_, tag)
+    }
+
+    fn constant(&self, f: &mut fmt::Formatter, constr: &C::Constant) -> fmt::Result {
+        write!(f, "{:?}", constr)
+    }
+
+    fn infinite(&self, f: &mut fmt::Formatter, value: &C::Infinite) -> fmt::Result {
+        write!(f, "{:?}", value)
+    }
+
+    fn opaque(&self, f: &mut fmt::Formatter, constant: &C::Opaque) -> fmt::Result {
+        write!(f, "{:?}", constant)
+    }
+
+    fn wildcard(&self, f: &mut fmt::Formatter, wc: &C::Wildcard) -> fmt::Result {
+        let _ = wc;
+        write!(f, "_")
+    }
+
+    fn signed(&self, f: &mut fmt::Formatter, n: i128) -> fmt::Result {
+        write!(f, "{}", n)
+    }
+
+    fn unsigned(&self, f: &mut fmt::Formatter, n: u128) -> fmt::Result {
+        write!(f, "{}", n)
+    }
+
+    fn char(&self, f: &mut fmt::Formatter, c: char) -> fmt::Result {
+        write!(f, "{:?}", c)
+    }
+
+    fn float(&self, f: &mut fmt::Formatter, x: f64) -> fmt::Result {
+        write!(f, "{:?}", x)
+    }
+}
+
+/// Prints patterns using the defaults of `PatternPrinter`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultPrinter;
+
+impl<C: Constructors> PatternPrinter<C> for DefaultPrinter {}
+
+// the printer used by `Display`, which names the sum types by their own `Display`
+struct DisplayPrinter;
+
+impl<S: fmt::Display, C: Constructors<SumType = S>> PatternPrinter<C> for DisplayPrinter {
+    fn variant(&self, f: &mut fmt::Formatter, type_: &C::SumType, tag: u64) -> fmt::Result {
+        write!(f, "{}[{}]", type_, tag)
+    }
+}
+
+/// A pattern printed by a `PatternPrinter`, as returned by `Pattern::display`
+pub struct Printed<'a, C: Constructors, P> {
+    pattern: &'a Pattern<C>,
+    printer: &'a P,
+}
+
+impl<C: Constructors> Pattern<C> {
+    /// Prints the pattern with names from the printer
+    pub fn display<'a, P: PatternPrinter<C>>(&'a self, printer: &'a P) -> Printed<'a, C, P> {
+        Printed {
+            pattern: self,
+            printer,
+        }
+    }
+}
+
+impl<C: Constructors, P: PatternPrinter<C>> fmt::Display for Printed<'_, C, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        print(self.printer, f, self.pattern, false)
+    }
+}
+
+impl<S: fmt::Display, C: Constructors<SumType = S>> fmt::Display for Pattern<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        print(&DisplayPrinter, f, self, false)
+    }
+}
+
+/// Prints the constructor as a pattern without any parameters
+impl<S: fmt::Display, C: Constructors<SumType = S>> fmt::Display for Constructor<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        print_applied(&DisplayPrinter, f, self, &[], false)
+    }
+}
+
+// prints the pattern, parenthesizing it if it's `nested` as the parameter of another pattern and
+// would otherwise be ambiguous
+fn print<C: Constructors, P: PatternPrinter<C>>(
+    printer: &P,
+    f: &mut fmt::Formatter,
+    p: &Pattern<C>,
+    nested: bool,
+) -> fmt::Result {
+    print_applied(printer, f, &p.constr, &p.params, nested)
+}
+
+fn print_applied<C: Constructors, P: PatternPrinter<C>>(
+    printer: &P,
+    f: &mut fmt::Formatter,
+    constr: &Constructor<C>,
+    params: &[Pattern<C>],
+    nested: bool,
+) -> fmt::Result {
+    match constr {
+        Constructor::Or => parenthesized(f, nested, |f| {
+            separated(f, params, " | ", |f, alt| print(printer, f, alt, false))
+        }),
+        Constructor::Constant(constr) if field_names(constr, params.len()).is_some() => {
+            let names = field_names(constr, params.len()).unwrap();
+            let fields = params
+                .iter()
+                .zip(names)
+                .filter(|(param, _)| !matches!(param.constr, Constructor::Wildcard(_)))
+                .collect::<Vec<_>>();
+
+            write!(f, "{{ ")?;
+            separated(f, &fields, ", ", |f, (param, name)| {
+                write!(f, "{}: ", name)?;
+                print(printer, f, param, false)
+            })?;
+            match (fields.len() == params.len(), fields.is_empty()) {
+                (true, _) => write!(f, " }}"),
+                (false, true) => write!(f, ".. }}"),
+                (false, false) => write!(f, ", .. }}"),
+            }
+        }
+        Constructor::Variant { type_, tag } => applied(printer, f, params, nested, |f| {
+            printer.variant(f, type_, *tag)
+        }),
+        Constructor::Constant(constr) => {
+            applied(printer, f, params, nested, |f| printer.constant(f, constr))
+        }
+        Constructor::Lenghted(_) => {
+            write!(f, "[")?;
+            separated(f, params, ", ", |f, param| print(printer, f, param, false))?;
+            write!(f, "]")
+        }
+        Constructor::LengthedRest { rest_at, .. } => print_rest(printer, f, params, *rest_at),
+        Constructor::LengthRange {
+            lengths, rest_at, ..
+        } => parenthesized(f, nested, |f| {
+            if !params.is_empty() {
+                print_rest(printer, f, params, *rest_at)?;
+                write!(f, " ")?;
+            }
+            match (*lengths.start(), *lengths.end()) {
+                (start, end) if start == end => write!(f, "len {}", start),
+                (start, usize::MAX) => write!(f, "len {}..", start),
+                (start, end) => write!(f, "len {}..={}", start, end),
+            }
+        }),
+        Constructor::SignedInteger { range, .. } => {
+            let single = range.start() == range.end();
+            let negative = *range.start() < 0;
+            print_range(f, range, single, nested && (!single || negative), |f, n| {
+                printer.signed(f, n)
+            })
+        }
+        Constructor::UnsignedInteger { range, .. } => {
+            let single = range.start() == range.end();
+            print_range(f, range, single, nested && !single, |f, n| {
+                printer.unsigned(f, n)
+            })
+        }
+        Constructor::Char { range } => {
+            let single = range.start() == range.end();
+            print_range(f, range, single, nested && !single, |f, c| {
+                printer.char(f, c)
+            })
+        }
+        Constructor::Float { range } => {
+            // NaN isn't equal to itself, so `NaN..=NaN` is recognized by its bits
+            let single = range.start().to_bits() == range.end().to_bits();
+            let negative = *range.start() < 0.0;
+            print_range(f, range, single, nested && (!single || negative), |f, x| {
+                printer.float(f, x)
+            })
+        }
+        Constructor::Infinite(value) => printer.infinite(f, value),
+        Constructor::InfiniteAffix { prefix, suffix } => parenthesized(f, nested, |f| {
+            if let Some(prefix) = prefix {
+                printer.infinite(f, prefix)?;
+                write!(f, " ++ ")?;
+            }
+            printer.wildcard(f, &C::Wildcard::default())?;
+            if let Some(suffix) = suffix {
+                write!(f, " ++ ")?;
+                printer.infinite(f, suffix)?;
+            }
+            Ok(())
+        }),
+        Constructor::Opaque(constant) => printer.opaque(f, constant),
+        Constructor::Wildcard(wc) => printer.wildcard(f, wc),
+    }
+}
+
+// a constructor followed by its parameters, which needs parentheses as a parameter itself
+fn applied<C: Constructors, P: PatternPrinter<C>>(
+    printer: &P,
+    f: &mut fmt::Formatter,
+    params: &[Pattern<C>],
+    nested: bool,
+    head: impl FnOnce(&mut fmt::Formatter) -> fmt::Result,
+) -> fmt::Result {
+    parenthesized(f, nested && !params.is_empty(), |f| {
+        head(f)?;
+        params.iter().try_for_each(|param| {
+            write!(f, " ")?;
+            print(printer, f, param, true)
+        })
+    })
+}
+
+// a lengthed value like `[first, .., last]` with the rest placed after the first `rest_at` params
+fn print_rest<C: Constructors, P: PatternPrinter<C>>(
+    printer: &P,
+    f: &mut fmt::Formatter,
+    params: &[Pattern<C>],
+    rest_at: usize,
+) -> fmt::Result {
+    let (prefix, suffix) = params.split_at(rest_at);
+    let params = prefix
+        .iter()
+        .map(Some)
+        .chain([None])
+        .chain(suffix.iter().map(Some));
+    write!(f, "[")?;
+    separated(
+        f,
+        &params.collect::<Vec<_>>(),
+        ", ",
+        |f, param| match param {
+            Some(param) => print(printer, f, param, false),
+            None => write!(f, ".."),
+        },
+    )?;
+    write!(f, "]")
+}
+
+fn parenthesized(
+    f: &mut fmt::Formatter,
+    parenthesize: bool,
+    inner: impl FnOnce(&mut fmt::Formatter) -> fmt::Result,
+) -> fmt::Result {
+    if parenthesize {
+        write!(f, "(")?;
+        inner(f)?;
+        write!(f, ")")
+    } else {
+        inner(f)
+    }
+}
+
+fn separated<T>(
+    f: &mut fmt::Formatter,
+    items: &[T],
+    separator: &str,
+    mut print: impl FnMut(&mut fmt::Formatter, &T) -> fmt::Result,
+) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i != 0 {
+            write!(f, "{}", separator)?;
+        }
+        print(f, item)?;
+    }
+    Ok(())
+}
+
+// a range or the single value it contains, which as a parameter needs parentheses unless it's a
+// single value without a sign
+fn print_range<N: Copy>(
+    f: &mut fmt::Formatter,
+    range: &RangeInclusive<N>,
+    single: bool,
+    parenthesize: bool,
+    bound: impl Fn(&mut fmt::Formatter, N) -> fmt::Result,
+) -> fmt::Result {
+    parenthesized(f, parenthesize, |f| {
+        bound(f, *range.start())?;
+        if !single {
+            write!(f, "..=")?;
+            bound(f, *range.end())?;
+        }
+        Ok(())
+    })
+}
+
+// the names of the fields if the constant is a record
+fn field_names<C: ConstantConstructor>(constr: &C, params: usize) -> Option<Vec<&str>> {
+    if params == 0 {
+        return None;
+    }
+    (0..params).map(|field| constr.field_name(field)).collect()
+}
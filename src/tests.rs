@@ -1,32 +1,75 @@
+use super::tree::Branch;
 use super::{
-    ConstantConstructor, Constructor, Constructors, IsReachable, Pattern, PatternTree,
-    SumtypeConstructor,
+    ConstantConstructor, Constructor, Constructors, Decision, DefaultPrinter, InfiniteConstructor,
+    IsReachable, LengthedConstructor, MergeError, MergeErrorKind, Node, Param, Pattern,
+    PatternPrinter, PatternTree, Reachability, RecordError, SumtypeConstructor,
 };
 use std::ops::RangeInclusive;
+use std::sync::Arc;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
-struct Tuple(usize);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Constant {
+    Tuple(usize),
+    // a record with the fields `x`, `y` and `z`
+    Point,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Lengthed {
+    List,
+    // an array of at most this many elements
+    Array(usize),
+}
+
+impl LengthedConstructor for Lengthed {
+    fn max_length(&self) -> Option<usize> {
+        match self {
+            Lengthed::List => None,
+            Lengthed::Array(max) => Some(*max),
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum Infinite {
     String(&'static str),
 }
 
+impl InfiniteConstructor for Infinite {
+    fn starts_with(&self, prefix: &Self) -> bool {
+        let (Infinite::String(text), Infinite::String(prefix)) = (self, prefix);
+        text.starts_with(prefix)
+    }
+
+    fn ends_with(&self, suffix: &Self) -> bool {
+        let (Infinite::String(text), Infinite::String(suffix)) = (self, suffix);
+        text.ends_with(suffix)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct SumType(&'static str, usize);
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct MyConstructors;
 
 impl Constructors for MyConstructors {
-    type Constant = Tuple;
-    type Lengthed = ();
+    type Constant = Constant;
+    type Lengthed = Lengthed;
     type SumType = SumType;
     type Infinite = Infinite;
+    type Opaque = &'static str;
     type Wildcard = Wildcard;
+    // a label standing in for the span of the pattern
+    type Meta = &'static str;
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Wildcard(&'static str);
 
 impl Default for Wildcard {
@@ -39,6 +82,9 @@ impl SumtypeConstructor for SumType {
     fn max(&self) -> u64 {
         match self.0 {
             "option" => 1,
+            "unit" => 0,
+            "open" => 1,
+            "maybe_never" => 1,
             _ => panic!("type not found: {}", self.0),
         }
     }
@@ -47,14 +93,47 @@ impl SumtypeConstructor for SumType {
         match (self.0, tag) {
             ("option", 0) => 1, // just takes 1 params
             ("option", 1) => 0, // none takes 0 params
+            ("unit", 0) => 0,
+            ("open", 0 | 1) => 0,
+            ("maybe_never", 0) => 1,
+            ("maybe_never", 1) => 0,
             _ => panic!("type not found: {}", self.0),
         }
     }
+
+    fn is_non_exhaustive(&self) -> bool {
+        self.0 == "open"
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0 == "never"
+    }
+
+    // `maybe_never` is `Option<Never>`
+    fn is_inhabited(&self, tag: u64) -> bool {
+        !(self.0 == "maybe_never" && tag == 0)
+    }
+}
+
+impl std::fmt::Display for SumType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
-impl ConstantConstructor for Tuple {
+impl ConstantConstructor for Constant {
     fn len_requirement(&self) -> usize {
-        self.0
+        match self {
+            Constant::Tuple(len) => *len,
+            Constant::Point => 3,
+        }
+    }
+
+    fn field_name(&self, field: usize) -> Option<&str> {
+        match self {
+            Constant::Tuple(_) => None,
+            Constant::Point => ["x", "y", "z"].get(field).copied(),
+        }
     }
 }
 
@@ -65,6 +144,21 @@ fn int(range: RangeInclusive<i64>) -> Pattern<MyConstructors> {
     })
 }
 
+fn uint(range: RangeInclusive<u8>) -> Pattern<MyConstructors> {
+    Pattern::new(Constructor::UnsignedInteger {
+        bitsize: 8,
+        range: *range.start() as u128..=*range.end() as u128,
+    })
+}
+
+fn chars(range: RangeInclusive<char>) -> Pattern<MyConstructors> {
+    Pattern::new(Constructor::Char { range })
+}
+
+fn float(range: RangeInclusive<f64>) -> Pattern<MyConstructors> {
+    Pattern::new(Constructor::Float { range })
+}
+
 fn variant(
     variant: u64,
     max: usize,
@@ -89,14 +183,79 @@ fn none() -> Pattern<MyConstructors> {
     variant(1, 1, "option", vec![])
 }
 
+fn point(fields: Vec<(&str, Pattern<MyConstructors>)>) -> Pattern<MyConstructors> {
+    Pattern::record(Constant::Point, fields).unwrap()
+}
+
+fn opaque(name: &'static str) -> Pattern<MyConstructors> {
+    Pattern::new(Constructor::Opaque(name))
+}
+
+fn open(tag: u64) -> Pattern<MyConstructors> {
+    variant(tag, 1, "open", vec![])
+}
+
 fn tuple<const N: usize>(params: [Pattern<MyConstructors>; N]) -> Pattern<MyConstructors> {
-    Pattern::new(Constructor::Constant(Tuple(params.len()))).with_params(params.to_vec())
+    Pattern::new(Constructor::Constant(Constant::Tuple(params.len()))).with_params(params.to_vec())
+}
+
+fn or<const N: usize>(alternatives: [Pattern<MyConstructors>; N]) -> Pattern<MyConstructors> {
+    Pattern::or(alternatives.to_vec())
+}
+
+fn list<const N: usize>(params: [Pattern<MyConstructors>; N]) -> Pattern<MyConstructors> {
+    Pattern::new(Constructor::Lenghted(Lengthed::List)).with_params(params.to_vec())
+}
+
+// a list pattern with the rest placed between `prefix` and `suffix`
+fn list_rest<const N: usize, const M: usize>(
+    prefix: [Pattern<MyConstructors>; N],
+    suffix: [Pattern<MyConstructors>; M],
+) -> Pattern<MyConstructors> {
+    Pattern::new(Constructor::LengthedRest {
+        constr: Lengthed::List,
+        rest_at: N,
+    })
+    .with_params(prefix.into_iter().chain(suffix).collect())
+}
+
+// an array pattern of at most `max` elements, with a rest after the given ones if `rest` is set
+fn array<const N: usize>(
+    max: usize,
+    params: [Pattern<MyConstructors>; N],
+    rest: bool,
+) -> Pattern<MyConstructors> {
+    let constr = match rest {
+        true => Constructor::LengthedRest {
+            constr: Lengthed::Array(max),
+            rest_at: N,
+        },
+        false => Constructor::Lenghted(Lengthed::Array(max)),
+    };
+    Pattern::new(constr).with_params(params.to_vec())
+}
+
+// a list pattern of any length within the range
+fn len(lengths: RangeInclusive<usize>) -> Pattern<MyConstructors> {
+    Pattern::new(Constructor::LengthRange {
+        constr: Lengthed::List,
+        lengths,
+        rest_at: 0,
+    })
 }
 
 fn string(text: &'static str) -> Pattern<MyConstructors> {
     Pattern::new(Constructor::Infinite(Infinite::String(text)))
 }
 
+// a string pattern like `"http://" ++ _` or `_ ++ ".lm"`
+fn affixed(prefix: Option<&'static str>, suffix: Option<&'static str>) -> Pattern<MyConstructors> {
+    Pattern::new(Constructor::InfiniteAffix {
+        prefix: prefix.map(Infinite::String),
+        suffix: suffix.map(Infinite::String),
+    })
+}
+
 macro_rules! assert_reach {
     ($tree:ident, $pat:expr, $exp:expr) => {
         println!(" ** inserting {:?}\n", &$pat);
@@ -230,6 +389,8 @@ fn min_max() {
         i64::MIN as i128..=i64::MAX as i128
     );
     assert_eq!(unsigned_max(32), u32::MAX as u128);
+    assert_eq!(unsigned_max(128), u128::MAX);
+    assert_eq!(signed_min(128)..=signed_max(128), i128::MIN..=i128::MAX);
 }
 
 #[test]
@@ -265,3 +426,1356 @@ fn empty_wildcard() {
         panic!("{:?}", missing);
     }
 }
+
+#[test]
+fn unsigned_numbers() {
+    let mut tree = PatternTree::from_pattern(&uint(3..=5));
+    println!(" !! init tree:\n{}", &tree);
+    assert_reach!(tree, uint(2..=7), IsReachable(true));
+    assert_reach!(tree, uint(4..=6), IsReachable(false));
+    assert_reach!(tree, uint(0..=9), IsReachable(true));
+    assert!(!tree.is_exhaustive());
+    assert!(!tree.generate_missing_patterns().is_empty());
+    assert_reach!(tree, uint(10..=255), IsReachable(true));
+    assert_reach!(tree, wildcard("_"), IsReachable(false));
+    assert!(tree.is_exhaustive() && dbg!(tree.generate_missing_patterns()).is_empty());
+}
+
+#[test]
+fn unsigned_wildcards() {
+    let mut tree = PatternTree::from_pattern(&just(uint(0..=0)));
+    assert_reach!(tree, just(wildcard("n")), IsReachable(true));
+    assert_reach!(tree, just(uint(u8::MAX..=u8::MAX)), IsReachable(false));
+    assert_reach!(tree, none(), IsReachable(true));
+    assert!(tree.is_exhaustive());
+
+    let mut tree = PatternTree::from_pattern(&tuple([wildcard("_"), uint(1..=1)]));
+    assert_reach!(tree, tuple([uint(0..=0), wildcard("_")]), IsReachable(true));
+    assert_reach!(
+        tree,
+        tuple([uint(0..=255), uint(1..=1)]),
+        IsReachable(false)
+    );
+    assert_reach!(tree, tuple([uint(0..=0), uint(2..=2)]), IsReachable(false));
+    assert_reach!(tree, tuple([uint(1..=1), uint(2..=2)]), IsReachable(true));
+}
+
+#[test]
+fn missing_ranges() {
+    let mut tree = PatternTree::from_pattern(&int(0..=5));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![int(i64::MIN..=-1), int(6..=i64::MAX)]
+    );
+
+    assert_reach!(tree, int(10..=20), IsReachable(true));
+    assert_reach!(tree, int(i64::MIN..=-1), IsReachable(true));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![int(6..=9), int(21..=i64::MAX)]
+    );
+
+    let mut tree = PatternTree::from_pattern(&uint(0..=0));
+    assert_reach!(tree, uint(255..=255), IsReachable(true));
+    assert_eq!(tree.generate_missing_patterns(), vec![uint(1..=254)]);
+
+    let mut tree = PatternTree::from_pattern(&just(uint(1..=255)));
+    assert_reach!(tree, none(), IsReachable(true));
+    assert_eq!(tree.generate_missing_patterns(), vec![just(uint(0..=0))]);
+}
+
+#[test]
+fn nested_missing() {
+    let mut tree = PatternTree::from_pattern(&just(just(int(0..=0))));
+    assert_reach!(tree, none(), IsReachable(true));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![
+            just(just(int(i64::MIN..=-1))),
+            just(just(int(1..=i64::MAX))),
+            just(none())
+        ]
+    );
+
+    let mut tree = PatternTree::from_pattern(&tuple([just(wildcard("_")), none()]));
+    assert_reach!(tree, tuple([none(), wildcard("_")]), IsReachable(true));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![tuple([just(wildcard("_")), just(wildcard("_"))])]
+    );
+
+    let mut tree = PatternTree::from_pattern(&tuple([tuple([int(0..=0), none()]), int(1..=1)]));
+    assert_reach!(
+        tree,
+        tuple([wildcard("_"), int(2..=i64::MAX)]),
+        IsReachable(true)
+    );
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![
+            tuple([tuple([int(0..=0), just(wildcard("_"))]), int(i64::MIN..=1)]),
+            tuple([tuple([int(0..=0), none()]), int(i64::MIN..=0)]),
+            tuple([
+                tuple([int(i64::MIN..=-1), wildcard("_")]),
+                int(i64::MIN..=1)
+            ]),
+            tuple([tuple([int(1..=i64::MAX), wildcard("_")]), int(i64::MIN..=1)]),
+        ]
+    );
+}
+
+#[test]
+fn wildcard_then_variant() {
+    let mut tree = PatternTree::from_pattern(&tuple([wildcard("_"), int(0..=0)]));
+    assert_reach!(tree, tuple([none(), wildcard("_")]), IsReachable(true));
+    assert_reach!(
+        tree,
+        tuple([just(wildcard("_")), int(0..=0)]),
+        IsReachable(false)
+    );
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![
+            tuple([just(wildcard("_")), int(i64::MIN..=-1)]),
+            tuple([just(wildcard("_")), int(1..=i64::MAX)]),
+        ]
+    );
+}
+
+#[test]
+fn mismatched_patterns() {
+    let mut tree = PatternTree::from_pattern(&tuple([int(0..=0), just(int(0..=0))]));
+    let before = tree.to_string();
+
+    let err = tree.try_include_pattern(&tuple([int(0..=0), just(string("a"))]));
+    assert_eq!(
+        err,
+        Err(MergeError {
+            path: vec![1, 0],
+            kind: MergeErrorKind::KindMismatch,
+            meta: ""
+        })
+    );
+
+    let err = tree.try_include_pattern(&tuple([wildcard("_"), just(uint(0..=0))]));
+    assert_eq!(
+        err,
+        Err(MergeError {
+            path: vec![1, 0],
+            kind: MergeErrorKind::KindMismatch,
+            meta: ""
+        })
+    );
+
+    let small = Pattern::new(Constructor::SignedInteger {
+        range: 0..=0,
+        bitsize: 8,
+    });
+    let err = tree.try_include_pattern(&tuple([small, none()]));
+    assert_eq!(
+        err,
+        Err(MergeError {
+            path: vec![0],
+            kind: MergeErrorKind::BitsizeMismatch {
+                expected: 64,
+                found: 8
+            },
+            meta: "",
+        })
+    );
+
+    let other = variant(0, 0, "unit", vec![]);
+    let err = tree.try_include_pattern(&tuple([int(0..=0), other]));
+    assert_eq!(
+        err,
+        Err(MergeError {
+            path: vec![1],
+            kind: MergeErrorKind::SumTypeMismatch,
+            meta: ""
+        })
+    );
+
+    let err = tree.try_include_pattern(&tuple([int(1..=1), none(), none()]));
+    assert_eq!(
+        err,
+        Err(MergeError {
+            path: vec![],
+            kind: MergeErrorKind::ArityMismatch,
+            meta: ""
+        })
+    );
+
+    let err = tree.try_include_pattern(&tuple([int(RangeInclusive::new(5, 1)), none()]));
+    assert_eq!(
+        err,
+        Err(MergeError {
+            path: vec![0],
+            kind: MergeErrorKind::InvalidRange,
+            meta: ""
+        })
+    );
+    let swapped = float(1.0..=0.0).with_meta("swapped");
+    let err = tree.try_include_pattern(&or([tuple([int(0..=0), none()]), swapped]));
+    assert_eq!(
+        err,
+        Err(MergeError {
+            path: vec![1],
+            kind: MergeErrorKind::InvalidRange,
+            meta: "swapped"
+        })
+    );
+    let err = tree.try_include_pattern(&tuple([int(0..=0), just(float(f64::NAN..=1.0))]));
+    assert_eq!(err.unwrap_err().kind, MergeErrorKind::InvalidRange);
+
+    // integers outside of their bitsize, and bitsizes no integer has
+    let bits = |bitsize, range| Pattern::new(Constructor::SignedInteger { range, bitsize });
+    for p in [
+        bits(8, -129..=0),
+        bits(8, 0..=128),
+        bits(0, 0..=0),
+        bits(129, 0..=0),
+    ] {
+        let err = tree.try_include_pattern(&tuple([p, none()]));
+        assert_eq!(err.unwrap_err().kind, MergeErrorKind::InvalidRange);
+    }
+    let unsigned = Constructor::UnsignedInteger {
+        range: 0..=256,
+        bitsize: 8,
+    };
+    let err = tree.try_include_pattern(&tuple([int(0..=0), just(Pattern::new(unsigned))]));
+    assert_eq!(err.unwrap_err().kind, MergeErrorKind::InvalidRange);
+
+    assert_eq!(tree.to_string(), before);
+    assert_eq!(
+        tree.try_include_pattern(&tuple([int(1..=1), none()])),
+        Ok(IsReachable(true))
+    );
+
+    let mut tree = PatternTree::from_pattern(&tuple([list([none(), none(), none()]), none()]));
+    let err = tree.try_include_pattern(&tuple([list_rest([none()], [int(0..=0)]), none()]));
+    assert_eq!(
+        err,
+        Err(MergeError {
+            path: vec![0, 1],
+            kind: MergeErrorKind::KindMismatch,
+            meta: ""
+        })
+    );
+    let err = tree.try_include_pattern(&tuple([list_rest([none()], []), int(0..=0)]));
+    assert_eq!(
+        err,
+        Err(MergeError {
+            path: vec![1],
+            kind: MergeErrorKind::KindMismatch,
+            meta: ""
+        })
+    );
+}
+
+#[test]
+fn or_patterns() {
+    let mut tree = PatternTree::from_pattern(&just(or([int(0..=0), int(2..=2)])));
+    assert_reach!(tree, just(int(2..=2)), IsReachable(false));
+    assert_reach!(tree, just(int(1..=1)), IsReachable(true));
+    assert_eq!(
+        tree.include_alternatives(&or([just(int(0..=2)), none(), just(wildcard("_"))])),
+        Reachability {
+            is_reachable: IsReachable(true),
+            redundant_alternatives: vec![vec![0]],
+            redundant_meta: vec![""],
+            covered_by: vec![]
+        }
+    );
+    assert!(tree.is_exhaustive());
+
+    let mut tree = PatternTree::from_pattern(&tuple([int(0..=0), none()]));
+    assert_eq!(
+        tree.include_alternatives(&tuple([
+            or([int(0..=0), int(1..=1)]),
+            or([none(), just(or([string("a"), string("b"), string("a")]))])
+        ])),
+        Reachability {
+            is_reachable: IsReachable(true),
+            redundant_alternatives: vec![vec![1, 1, 0, 2]],
+            redundant_meta: vec![""],
+            covered_by: vec![]
+        }
+    );
+    assert_eq!(
+        tree.include_alternatives(&or([
+            tuple([int(1..=1), none()]),
+            tuple([int(2..=2), none()])
+        ])),
+        Reachability {
+            is_reachable: IsReachable(true),
+            redundant_alternatives: vec![vec![0]],
+            redundant_meta: vec![""],
+            covered_by: vec![]
+        }
+    );
+    assert_eq!(
+        tree.try_include_alternatives(&tuple([
+            or([int(3..=3), int(0..=0)]),
+            or([none(), string("a")])
+        ])),
+        Err(MergeError {
+            path: vec![1, 1],
+            kind: MergeErrorKind::KindMismatch,
+            meta: ""
+        })
+    );
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![
+            tuple([int(i64::MIN..=-1), wildcard("_")]),
+            tuple([int(3..=i64::MAX), wildcard("_")]),
+            tuple([int(0..=0), just(wildcard("_"))]),
+            tuple([int(1..=1), just(wildcard("_"))]),
+            tuple([int(2..=2), just(wildcard("_"))]),
+        ]
+    );
+}
+
+#[test]
+fn many_alternatives() {
+    // every one of the 1024 combinations is included on its own
+    let option = || or([none(), or([just(wildcard("_"))])]);
+    let p = tuple(std::array::from_fn::<_, 10, _>(|_| option()));
+    let mut tree = PatternTree::from_pattern(&p);
+    assert!(tree.is_exhaustive());
+
+    let reachability = tree.include_alternatives(&p);
+    assert_eq!(reachability.is_reachable, IsReachable(false));
+    assert_eq!(reachability.redundant_alternatives.len(), 10 * 2);
+    assert_eq!(reachability.covered_by, vec![0]);
+}
+
+#[test]
+fn guarded_patterns() {
+    let mut tree = PatternTree::from_pattern(&just(int(0..=0)));
+    assert_eq!(
+        tree.check_guarded_pattern(&just(wildcard("n"))),
+        IsReachable(true)
+    );
+    assert_eq!(
+        tree.check_guarded_pattern(&just(int(0..=0))),
+        IsReachable(false)
+    );
+    assert_eq!(
+        tree.check_guarded_alternatives(&or([just(int(0..=0)), none()])),
+        Reachability {
+            is_reachable: IsReachable(true),
+            redundant_alternatives: vec![vec![0]],
+            redundant_meta: vec![""],
+            covered_by: vec![]
+        }
+    );
+
+    assert_eq!(
+        tree.check_guarded_pattern(&wildcard("_")),
+        IsReachable(true)
+    );
+    assert!(!tree.is_exhaustive());
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![just(int(i64::MIN..=-1)), just(int(1..=i64::MAX)), none()]
+    );
+
+    // the same pattern without a guard is still reachable after a guarded one
+    assert_reach!(tree, wildcard("_"), IsReachable(true));
+    assert_eq!(tree.check_guarded_pattern(&none()), IsReachable(false));
+    assert!(tree.is_exhaustive());
+
+    // guarded arms use up their arm, even as the first arm of the match
+    let mut tree = PatternTree::new();
+    assert_eq!(tree.check_guarded_pattern(&none()), IsReachable(true));
+    assert!(tree.arms().is_empty());
+    assert_reach!(tree, just(int(0..=5)), IsReachable(true));
+    assert_eq!(
+        tree.check_guarded_pattern(&just(wildcard("n"))),
+        IsReachable(true)
+    );
+    assert_reach!(tree, wildcard("_"), IsReachable(true));
+    assert_eq!(tree.arms(), vec![1, 3]);
+    assert_eq!(tree.arms_matching(&just(int(3..=3))), vec![1]);
+    assert_eq!(tree.arms_matching(&just(int(6..=6))), vec![3]);
+    assert_eq!(tree.arms_matching(&none()), vec![3]);
+}
+
+#[test]
+fn shared_nodes() {
+    let mut tree = PatternTree::from_pattern(&just(int(0..=5)));
+    tree.include_pattern(&none());
+    let before = tree.clone();
+    assert_reach!(tree, just(int(6..=6)), IsReachable(true));
+
+    // only the continuations the pattern was merged into are copied
+    let (Node::Variant(_, old), Node::Variant(_, new)) = (before.root(), tree.root()) else {
+        panic!("expected a variant node");
+    };
+    let con = |branches: &[Branch<MyConstructors, u64>], tag| {
+        branches.iter().find(|b| b.data == tag).unwrap().con.clone()
+    };
+    assert!(Arc::ptr_eq(&con(old, 1), &con(new, 1)));
+    assert!(!Arc::ptr_eq(&con(old, 0), &con(new, 0)));
+}
+
+#[test]
+fn arm_identity() {
+    let mut tree = PatternTree::from_arm(0, &just(int(0..=5)));
+    tree.include_arm(1, &just(int(3..=8)));
+    tree.include_arm(2, &just(int(4..=4)));
+    tree.include_arm(3, &just(wildcard("_")));
+    assert_eq!(tree.arms_matching(&just(int(3..=3))), vec![0]);
+    assert_eq!(tree.arms_matching(&just(int(7..=7))), vec![1]);
+    assert_eq!(tree.arms_matching(&just(int(9..=9))), vec![3]);
+    assert_eq!(tree.arms_matching(&just(int(5..=9))), vec![0, 1, 3]);
+    assert!(tree.arms_matching(&none()).is_empty());
+    assert_eq!(tree.arms(), vec![0, 1, 3]);
+
+    let mut tree = PatternTree::from_pattern(&tuple([string("a"), none()]));
+    assert_reach!(
+        tree,
+        tuple([wildcard("_"), just(int(0..=0))]),
+        IsReachable(true)
+    );
+    assert_reach!(tree, tuple([string("b"), wildcard("_")]), IsReachable(true));
+    assert_reach!(tree, tuple([string("a"), wildcard("_")]), IsReachable(true));
+    assert_eq!(
+        tree.arms_matching(&tuple([string("b"), just(int(0..=0))])),
+        vec![1]
+    );
+    assert_eq!(tree.arms_matching(&tuple([string("b"), none()])), vec![2]);
+    assert_eq!(
+        tree.arms_matching(&tuple([string("a"), just(int(1..=1))])),
+        vec![3]
+    );
+    assert_eq!(
+        tree.arms_matching(&tuple([string("c"), wildcard("_")])),
+        vec![1]
+    );
+    assert_eq!(tree.arms(), vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn decision_tree() {
+    let mut tree = PatternTree::from_pattern(&tuple([just(int(0..=5)), string("a")]));
+    tree.include_pattern(&tuple([wildcard("_"), string("b")]));
+    tree.include_pattern(&tuple([none(), wildcard("_")]));
+
+    let strings = |cases: Vec<(&'static str, usize)>, fallback| Decision::Infinite {
+        access: vec![Param::Index(1)],
+        cases: cases
+            .into_iter()
+            .map(|(str, arm)| (Infinite::String(str), Decision::Arm(arm)))
+            .collect(),
+        affixes: vec![],
+        fallback: Box::new(fallback),
+    };
+
+    assert_eq!(
+        tree.compile(),
+        Decision::Variant {
+            access: vec![Param::Index(0)],
+            type_: SumType("option", 1),
+            cases: vec![
+                (
+                    0,
+                    Decision::SignedInteger {
+                        access: vec![Param::Index(0), Param::Index(0)],
+                        bitsize: 64,
+                        cases: vec![
+                            (
+                                i64::MIN as i128..=-1,
+                                strings(vec![("b", 1)], Decision::Fail)
+                            ),
+                            (0..=5, strings(vec![("a", 0), ("b", 1)], Decision::Fail)),
+                            (
+                                6..=i64::MAX as i128,
+                                strings(vec![("b", 1)], Decision::Fail)
+                            ),
+                        ]
+                    }
+                ),
+                (1, strings(vec![("b", 1)], Decision::Arm(2))),
+            ]
+        }
+    );
+
+    let tree = PatternTree::from_pattern(&wildcard("_"));
+    assert_eq!(tree.compile(), Decision::Arm(0));
+}
+
+#[test]
+fn covered_by() {
+    let mut tree = PatternTree::from_pattern(&just(int(0..=5)));
+    tree.include_pattern(&none());
+    tree.include_pattern(&just(int(3..=10)));
+    tree.include_pattern(&just(int(20..=20)));
+
+    let report = tree.include_alternatives(&just(int(2..=7)));
+    assert_eq!(report.is_reachable, IsReachable(false));
+    assert_eq!(report.covered_by, vec![0, 2]);
+
+    let report = tree.include_alternatives(&or([none(), just(int(20..=20))]));
+    assert_eq!(report.is_reachable, IsReachable(false));
+    assert_eq!(report.covered_by, vec![1, 3]);
+
+    let report = tree.include_alternatives(&just(int(2..=11)));
+    assert_eq!(report.is_reachable, IsReachable(true));
+    assert!(report.covered_by.is_empty());
+
+    tree.include_pattern(&wildcard("_"));
+    let report = tree.include_alternatives(&just(int(12..=13)));
+    assert_eq!(report.covered_by, vec![7]);
+
+    // the unreachable arms before the covering one are counted as well
+    let mut tree = PatternTree::from_pattern(&none());
+    assert_reach!(tree, none(), IsReachable(false));
+    assert_reach!(tree, just(wildcard("_")), IsReachable(true));
+    let report = tree.include_alternatives(&just(int(3..=3)));
+    assert_eq!(report.is_reachable, IsReachable(false));
+    assert_eq!(report.covered_by, vec![2]);
+}
+
+#[test]
+fn slice_patterns() {
+    let mut tree = PatternTree::from_pattern(&list([]));
+    assert_reach!(
+        tree,
+        list_rest([just(wildcard("_"))], []),
+        IsReachable(true)
+    );
+    assert_reach!(tree, list([just(int(0..=0))]), IsReachable(false));
+    assert_reach!(
+        tree,
+        list_rest([wildcard("_")], [none()]),
+        IsReachable(true)
+    );
+    assert_reach!(tree, list([none(), none()]), IsReachable(false));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![
+            list([none()]),
+            list([none(), just(wildcard("_"))]),
+            list_rest([none(), wildcard("_")], [just(wildcard("_"))]),
+        ]
+    );
+
+    assert_reach!(
+        tree,
+        list_rest([none()], [just(wildcard("_"))]),
+        IsReachable(true)
+    );
+    assert_eq!(tree.generate_missing_patterns(), vec![list([none()])]);
+    assert_reach!(tree, list_rest([], [wildcard("_")]), IsReachable(true));
+    assert_reach!(tree, list_rest([none()], []), IsReachable(false));
+    assert!(tree.is_exhaustive());
+
+    let mut tree = PatternTree::from_pattern(&list([]));
+    tree.include_pattern(&list([wildcard("_")]));
+    tree.include_pattern(&list([wildcard("_"), wildcard("_")]));
+    tree.include_pattern(&list_rest([just(wildcard("_"))], []));
+
+    // lists of length 3 or more starting with `none`
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![list_rest([none(), wildcard("_"), wildcard("_")], [])]
+    );
+    assert_eq!(tree.arms_matching(&list_rest([], [none()])), vec![1, 2, 3]);
+
+    let mut tree = PatternTree::from_pattern(&list([none()]));
+    tree.include_pattern(&list_rest([wildcard("_"), wildcard("_")], []));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![list([]), list([just(wildcard("_"))])]
+    );
+}
+
+#[test]
+fn slice_decision_tree() {
+    let mut tree = PatternTree::from_pattern(&list([]));
+    tree.include_pattern(&list_rest([], [none()]));
+    tree.include_pattern(&wildcard("_"));
+
+    assert_eq!(
+        tree.compile(),
+        Decision::Length {
+            access: vec![],
+            constr: Lengthed::List,
+            cases: vec![
+                (0..=0, Decision::Arm(0)),
+                (
+                    1..=usize::MAX,
+                    Decision::Variant {
+                        access: vec![Param::FromEnd(1)],
+                        type_: SumType("option", 1),
+                        cases: vec![(0, Decision::Arm(2)), (1, Decision::Arm(1))],
+                    }
+                ),
+            ]
+        }
+    );
+}
+
+#[test]
+fn char_ranges() {
+    let mut tree = PatternTree::from_pattern(&chars('\0'..='\u{D7FF}'));
+    assert_reach!(tree, chars('a'..='z'), IsReachable(false));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![chars('\u{E000}'..=char::MAX)]
+    );
+
+    assert_reach!(tree, chars('\u{E000}'..='\u{FFFF}'), IsReachable(true));
+    assert_reach!(tree, chars('\u{10000}'..=char::MAX), IsReachable(true));
+    assert!(tree.is_exhaustive());
+
+    let mut tree = PatternTree::from_pattern(&chars('a'..='z'));
+    assert_reach!(tree, chars('A'..='Z'), IsReachable(true));
+    assert_reach!(tree, chars('m'..='n'), IsReachable(false));
+    assert_reach!(tree, chars('\u{D000}'..='\u{F000}'), IsReachable(true));
+
+    let missing = tree
+        .generate_missing_patterns()
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        missing,
+        vec![
+            "'\\0'..='@'",
+            "'['..='`'",
+            "'{'..='\u{cfff}'",
+            "'\\u{f001}'..='\\u{10ffff}'",
+        ]
+    );
+}
+
+#[test]
+fn float_ranges() {
+    let mut tree = PatternTree::from_pattern(&float(0.0..=1.0));
+    assert_reach!(tree, float(0.5..=0.75), IsReachable(false));
+    assert_reach!(tree, float(-0.0..=-0.0), IsReachable(false));
+    assert_reach!(tree, float(-1.0..=0.5), IsReachable(true));
+    assert_reach!(
+        tree,
+        float(f64::INFINITY..=f64::INFINITY),
+        IsReachable(true)
+    );
+    assert_reach!(
+        tree,
+        float(f64::NEG_INFINITY..=f64::INFINITY),
+        IsReachable(true)
+    );
+
+    // only NaN is left, which no range other than `NaN..=NaN` covers
+    assert!(!tree.is_exhaustive());
+    let missing = tree
+        .generate_missing_patterns()
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(missing, vec!["NaN"]);
+
+    assert_reach!(tree, float(f64::NAN..=f64::NAN), IsReachable(true));
+    assert!(tree.is_exhaustive());
+
+    let all = float(f64::NEG_INFINITY..=f64::INFINITY);
+    let tree = PatternTree::from_pattern(&or([all, float(f64::NAN..=f64::NAN)]));
+    assert!(tree.is_exhaustive());
+
+    let mut tree = PatternTree::from_pattern(&float(1.0..=2.0));
+    tree.include_pattern(&float(f64::NEG_INFINITY..=-0.0));
+    let missing = tree
+        .generate_missing_patterns()
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        missing,
+        vec![
+            "5e-324..=0.9999999999999999",
+            "2.0000000000000004..=inf",
+            "NaN"
+        ]
+    );
+
+    let mut tree = PatternTree::from_pattern(&tuple([float(0.0..=0.0), none()]));
+    assert_reach!(tree, tuple([wildcard("_"), none()]), IsReachable(true));
+    assert_reach!(
+        tree,
+        tuple([float(f64::NAN..=f64::NAN), none()]),
+        IsReachable(false)
+    );
+    assert_eq!(
+        tree.arms_matching(&tuple([float(-0.0..=0.0), none()])),
+        vec![0]
+    );
+}
+
+#[test]
+fn non_exhaustive_sum_types() {
+    let mut tree = PatternTree::from_pattern(&open(0));
+    assert_reach!(tree, open(1), IsReachable(true));
+    assert!(!tree.is_exhaustive());
+    assert_eq!(tree.generate_missing_patterns(), vec![wildcard("_")]);
+
+    assert_reach!(tree, wildcard("_"), IsReachable(true));
+    assert!(tree.is_exhaustive());
+
+    let mut tree = PatternTree::from_pattern(&tuple([wildcard("_"), none()]));
+    tree.include_pattern(&tuple([open(0), just(wildcard("_"))]));
+    tree.include_pattern(&tuple([open(1), just(wildcard("_"))]));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![tuple([wildcard("_"), just(wildcard("_"))])]
+    );
+    assert_reach!(
+        tree,
+        tuple([wildcard("_"), just(int(0..=0))]),
+        IsReachable(true)
+    );
+}
+
+#[test]
+fn uninhabited_sum_types() {
+    let tree = PatternTree::<MyConstructors>::empty(SumType("never", 0));
+    assert!(tree.is_exhaustive());
+    assert!(tree.generate_missing_patterns().is_empty());
+
+    let tree = PatternTree::<MyConstructors>::empty(SumType("option", 1));
+    assert!(!tree.is_exhaustive());
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![just(wildcard("_")), none()]
+    );
+
+    let mut tree = PatternTree::empty(SumType("maybe_never", 1));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![variant(1, 1, "maybe_never", vec![])]
+    );
+    assert_reach!(
+        tree,
+        variant(1, 1, "maybe_never", vec![]),
+        IsReachable(true)
+    );
+    assert!(tree.is_exhaustive());
+    assert!(tree.generate_missing_patterns().is_empty());
+
+    let never = || {
+        Pattern::new(Constructor::Variant {
+            type_: SumType("never", 0),
+            tag: 0,
+        })
+    };
+    assert_reach!(
+        tree,
+        variant(0, 1, "maybe_never", vec![never()]),
+        IsReachable(false)
+    );
+}
+
+#[test]
+fn record_patterns() {
+    let mut tree = PatternTree::from_pattern(&point(vec![("y", none())]));
+    assert_reach!(
+        tree,
+        point(vec![("z", none()), ("y", none())]),
+        IsReachable(false)
+    );
+    assert_reach!(tree, point(vec![("x", none())]), IsReachable(true));
+
+    let missing = tree
+        .generate_missing_patterns()
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(missing, vec!["{ x: option[0] _, y: option[0] _, .. }"]);
+
+    assert_reach!(
+        tree,
+        point(vec![("y", just(wildcard("_"))), ("x", just(wildcard("_")))]),
+        IsReachable(true)
+    );
+    assert!(tree.is_exhaustive());
+    assert_eq!(point(vec![]).to_string(), "{ .. }");
+    assert_eq!(
+        point(vec![("z", none())]).to_string(),
+        "{ z: option[1], .. }"
+    );
+
+    let err = Pattern::record(Constant::Point, vec![("w", none())]);
+    assert_eq!(err, Err(RecordError::UnknownField("w".to_string())));
+    let err = Pattern::record(Constant::Point, vec![("x", none()), ("x", none())]);
+    assert_eq!(err, Err(RecordError::DuplicateField("x".to_string())));
+}
+
+#[test]
+fn opaque_constants() {
+    let mut tree = PatternTree::from_pattern(&int(0..=5));
+    assert_reach!(tree, opaque("MAX"), IsReachable(true));
+    assert_reach!(tree, opaque("MAX"), IsReachable(true));
+
+    let report = tree.include_alternatives(&int(1..=1));
+    assert_eq!(report.is_reachable, IsReachable(false));
+    assert_eq!(report.covered_by, vec![0]);
+
+    let report = tree.include_alternatives(&or([opaque("MIN"), int(3..=3), int(6..=6)]));
+    assert_eq!(report.is_reachable, IsReachable(true));
+    assert_eq!(report.redundant_alternatives, vec![vec![1]]);
+    assert_eq!(tree.arms(), vec![0, 4]);
+    assert!(!tree.is_exhaustive());
+
+    assert_reach!(tree, wildcard("_"), IsReachable(true));
+    let report = tree.include_alternatives(&opaque("MAX"));
+    assert_eq!(report.is_reachable, IsReachable(false));
+    assert_eq!(report.covered_by, vec![0, 4, 5]);
+
+    let mut tree = PatternTree::from_pattern(&tuple([opaque("MAX"), none()]));
+    assert!(!tree.is_exhaustive());
+    assert_eq!(tree.generate_missing_patterns(), vec![wildcard("_")]);
+    assert_reach!(tree, tuple([int(0..=0), none()]), IsReachable(true));
+    assert_reach!(tree, tuple([wildcard("_"), none()]), IsReachable(true));
+    assert_reach!(tree, tuple([opaque("MIN"), none()]), IsReachable(false));
+}
+
+#[test]
+fn string_affixes() {
+    let mut tree = PatternTree::from_pattern(&affixed(Some("http://"), None));
+    assert_reach!(tree, string("http://x"), IsReachable(false));
+    assert_reach!(tree, string("https://x"), IsReachable(true));
+    assert_reach!(
+        tree,
+        affixed(Some("http://lumina"), None),
+        IsReachable(false)
+    );
+    assert_reach!(tree, affixed(None, Some(".lm")), IsReachable(true));
+    assert_reach!(tree, string("main.lm"), IsReachable(false));
+    assert_reach!(
+        tree,
+        affixed(Some("http://"), Some(".lm")),
+        IsReachable(false)
+    );
+    assert_reach!(tree, affixed(Some("ht"), None), IsReachable(true));
+    assert_reach!(tree, string("https://x"), IsReachable(false));
+    assert_reach!(tree, string("main.rs"), IsReachable(true));
+    assert!(!tree.is_exhaustive());
+    assert_reach!(tree, wildcard("_"), IsReachable(true));
+
+    assert_eq!(tree.arms_matching(&string("http://x.lm")), vec![0]);
+    assert_eq!(tree.arms_matching(&string("https://x")), vec![2]);
+    assert_eq!(tree.arms_matching(&string("a.lm")), vec![4]);
+    assert_eq!(tree.arms_matching(&string("htm")), vec![7]);
+    assert_eq!(tree.arms_matching(&string("main.rs")), vec![9]);
+    assert_eq!(tree.arms_matching(&string("x")), vec![10]);
+    assert_eq!(
+        tree.arms_matching(&affixed(Some("h"), None)),
+        vec![0, 2, 4, 7, 10]
+    );
+
+    // a prefix only covers the values it's contained in after it
+    let mut tree = PatternTree::from_pattern(&string("http://x"));
+    assert_reach!(tree, affixed(Some("http://"), None), IsReachable(true));
+    assert_reach!(tree, string("http://y"), IsReachable(false));
+    assert_reach!(tree, affixed(None, Some("x")), IsReachable(true));
+    assert_reach!(
+        tree,
+        affixed(Some("http://"), Some("x")),
+        IsReachable(false)
+    );
+
+    let mut tree = PatternTree::from_pattern(&tuple([affixed(Some("a"), None), none()]));
+    tree.include_pattern(&tuple([affixed(None, Some("b")), wildcard("_")]));
+    assert_reach!(
+        tree,
+        tuple([string("ab"), just(wildcard("_"))]),
+        IsReachable(false)
+    );
+    assert_reach!(
+        tree,
+        tuple([string("ax"), just(wildcard("_"))]),
+        IsReachable(true)
+    );
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![
+            tuple([wildcard("_"), wildcard("_")]),
+            tuple([affixed(Some("a"), None), just(wildcard("_"))]),
+        ]
+    );
+}
+
+#[test]
+fn default_affixes() {
+    // infinite types which opt out of affixes only start and end with themselves
+    #[derive(PartialEq)]
+    struct Symbol(u32);
+    impl InfiniteConstructor for Symbol {}
+
+    assert!(Symbol(1).starts_with(&Symbol(1)) && Symbol(1).ends_with(&Symbol(1)));
+    assert!(!Symbol(1).starts_with(&Symbol(2)) && !Symbol(1).ends_with(&Symbol(2)));
+}
+
+#[test]
+fn length_ranges() {
+    let mut tree = PatternTree::from_pattern(&len(0..=0));
+    assert_reach!(tree, len(4..=usize::MAX), IsReachable(true));
+    assert_eq!(tree.generate_missing_patterns(), vec![len(1..=3)]);
+
+    assert_reach!(tree, list([none()]), IsReachable(true));
+    let suffix = std::array::from_fn::<_, 5, _>(|_| wildcard("_"));
+    assert_reach!(tree, list_rest([], suffix.clone()), IsReachable(false));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![len(2..=3), list([just(wildcard("_"))])]
+    );
+
+    assert_reach!(tree, len(1..=3), IsReachable(true));
+    assert_reach!(
+        tree,
+        list([wildcard("_"), wildcard("_"), none()]),
+        IsReachable(false)
+    );
+    assert_reach!(tree, len(2..=usize::MAX), IsReachable(false));
+    assert!(tree.is_exhaustive());
+
+    assert_eq!(tree.arms_matching(&list([none(), none()])), vec![4]);
+    assert_eq!(tree.arms_matching(&list([just(int(0..=0))])), vec![4]);
+    assert_eq!(tree.arms_matching(&len(3..=5)), vec![1, 4]);
+
+    // the lengths of a range share their parameters instead of being spelled out one by one
+    let mut tree = PatternTree::from_pattern(&list_rest([none()], []));
+    assert_reach!(tree, len(0..=0), IsReachable(true));
+    assert_reach!(tree, len(6..=usize::MAX), IsReachable(true));
+    let missing = tree.generate_missing_patterns();
+    let constr = Constructor::LengthRange {
+        constr: Lengthed::List,
+        lengths: 1..=5,
+        rest_at: 1,
+    };
+    let expected = Pattern::new(constr).with_params(vec![just(wildcard("_"))]);
+    assert_eq!(missing, vec![expected]);
+    assert_eq!(missing[0].to_string(), "[option[0] _, ..] len 1..=5");
+
+    assert_reach!(tree, len(0..=200_000), IsReachable(true));
+    assert!(tree.generate_missing_patterns().is_empty());
+
+    // a single length keeps the shape of the rest instead of becoming a parameter per element
+    let mut tree = PatternTree::from_pattern(&list_rest([none()], []));
+    assert_reach!(tree, len(1_000_000..=1_000_000), IsReachable(true));
+    assert_reach!(tree, list_rest([], [none()]), IsReachable(true));
+    assert_eq!(tree.arms_matching(&len(999_999..=1_000_000)), vec![0, 1, 2]);
+}
+
+#[test]
+fn bounded_lengths() {
+    let mut tree = PatternTree::from_pattern(&array(3, [], false));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![array(3, [wildcard("_")], true)]
+    );
+
+    assert_reach!(tree, array(3, [wildcard("_")], false), IsReachable(true));
+    assert_reach!(
+        tree,
+        array(3, [wildcard("_"), wildcard("_")], false),
+        IsReachable(true)
+    );
+    assert!(!tree.is_exhaustive());
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![array(
+            3,
+            [wildcard("_"), wildcard("_"), wildcard("_")],
+            false
+        )]
+    );
+    assert_reach!(
+        tree,
+        array(3, [wildcard("_"), wildcard("_"), none()], false),
+        IsReachable(true)
+    );
+    assert_reach!(
+        tree,
+        array(3, [wildcard("_"), wildcard("_")], true),
+        IsReachable(true)
+    );
+    assert!(tree.is_exhaustive());
+
+    let mut tree = PatternTree::from_pattern(&array(3, [none()], false));
+    tree.include_pattern(&array(3, [wildcard("_"), wildcard("_")], true));
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![array(3, [], false), array(3, [just(wildcard("_"))], false)]
+    );
+    assert_eq!(
+        tree.compile(),
+        Decision::Length {
+            access: vec![],
+            constr: Lengthed::Array(3),
+            cases: vec![
+                (
+                    1..=1,
+                    Decision::Variant {
+                        access: vec![Param::Index(0)],
+                        type_: SumType("option", 1),
+                        cases: vec![(1, Decision::Arm(0))],
+                    }
+                ),
+                (2..=3, Decision::Arm(1)),
+            ]
+        }
+    );
+
+    let mut tree = PatternTree::from_pattern(&array(4, [], false));
+    let constr = Constructor::LengthRange {
+        constr: Lengthed::Array(4),
+        lengths: 1..=usize::MAX,
+        rest_at: 0,
+    };
+    assert_reach!(tree, Pattern::new(constr.clone()), IsReachable(true));
+    assert!(tree.is_exhaustive());
+}
+
+#[test]
+fn minimal_missing_patterns() {
+    let mut tree = PatternTree::from_pattern(&tuple([int(0..=5), none()]));
+    tree.include_pattern(&tuple([wildcard("_"), just(int(0..=0))]));
+    assert_eq!(tree.generate_missing_patterns().len(), 8);
+
+    let missing = tree.minimal_missing_patterns(None);
+    assert_eq!(
+        missing.patterns,
+        vec![
+            tuple([wildcard("_"), just(int(i64::MIN..=-1))]),
+            tuple([wildcard("_"), just(int(1..=i64::MAX))]),
+            tuple([int(i64::MIN..=-1), none()]),
+            tuple([int(6..=i64::MAX), none()]),
+        ]
+    );
+    assert_eq!(missing.more, 0);
+
+    let tree = PatternTree::<MyConstructors>::empty(SumType("option", 1));
+    assert_eq!(
+        tree.minimal_missing_patterns(None).patterns,
+        vec![wildcard("_")]
+    );
+
+    // the surrogates aren't chars, so the ranges around them are adjacent
+    let tree = PatternTree::from_pattern(&chars('a'..='a'));
+    let missing = tree.minimal_missing_patterns(Some(1));
+    assert_eq!(missing.patterns, vec![chars('\0'..='`')]);
+    assert_eq!(missing.to_string(), "'\\0'..='`' and 1 more");
+    let missing = tree.minimal_missing_patterns(None);
+    assert_eq!(missing.to_string(), "'\\0'..='`', 'b'..='\\u{10ffff}'");
+
+    // many witnesses which only differ in a single range are merged in one pass
+    let mut tree = PatternTree::from_pattern(&tuple([int(0..=0), just(int(0..=0))]));
+    for i in 1..300 {
+        tree.include_pattern(&tuple([int(i..=i), just(int(0..=0))]));
+    }
+    assert_eq!(tree.generate_missing_patterns().len(), 902);
+    assert_eq!(
+        tree.minimal_missing_patterns(None).patterns,
+        vec![
+            tuple([int(i64::MIN..=-1), wildcard("_")]),
+            tuple([int(300..=i64::MAX), wildcard("_")]),
+            tuple([int(0..=299), just(int(i64::MIN..=-1))]),
+            tuple([int(0..=299), just(int(1..=i64::MAX))]),
+            tuple([int(0..=299), none()]),
+        ]
+    );
+}
+
+// prints the patterns the way they'd be written in the source language
+struct SourcePrinter;
+
+impl PatternPrinter<MyConstructors> for SourcePrinter {
+    fn variant(&self, f: &mut std::fmt::Formatter, type_: &SumType, tag: u64) -> std::fmt::Result {
+        match (type_.0, tag) {
+            ("option", 0) => write!(f, "just"),
+            ("option", 1) => write!(f, "none"),
+            _ => write!(f, "{}[{}]", type_.0, tag),
+        }
+    }
+
+    fn infinite(&self, f: &mut std::fmt::Formatter, value: &Infinite) -> std::fmt::Result {
+        let Infinite::String(text) = value;
+        write!(f, "{:?}", text)
+    }
+
+    fn wildcard(&self, f: &mut std::fmt::Formatter, wc: &Wildcard) -> std::fmt::Result {
+        write!(f, "{}", wc.0)
+    }
+}
+
+#[test]
+fn pattern_printer() {
+    let p = tuple([just(just(wildcard("x"))), int(-3..=3), uint(7..=7)]);
+    assert_eq!(
+        p.to_string(),
+        "Tuple(3) (option[0] (option[0] _)) (-3..=3) 7"
+    );
+    assert_eq!(
+        p.display(&DefaultPrinter).to_string(),
+        "Tuple(3) (SumType(\"option\", 1)[0] \
+         (SumType(\"option\", 1)[0] _)) (-3..=3) 7"
+    );
+    assert_eq!(
+        p.display(&SourcePrinter).to_string(),
+        "Tuple(3) (just (just x)) (-3..=3) 7"
+    );
+    assert_eq!(
+        just(int(i32::MIN as i64..=0)).to_string(),
+        "option[0] (-2147483648..=0)"
+    );
+    assert_eq!(just(int(-3..=-3)).to_string(), "option[0] (-3)");
+    assert_eq!(just(float(-0.5..=-0.5)).to_string(), "option[0] (-0.5)");
+    assert_eq!(just(chars('a'..='z')).to_string(), "option[0] ('a'..='z')");
+    assert_eq!(int(-3..=3).to_string(), "-3..=3");
+
+    let p = just(or([none(), just(len(2..=usize::MAX))]));
+    assert_eq!(
+        p.display(&SourcePrinter).to_string(),
+        "just (none | just (len 2..))"
+    );
+    assert_eq!(len(1..=3).to_string(), "len 1..=3");
+
+    let p = list_rest([float(0.5..=0.5)], [chars('a'..='z'), list([])]);
+    assert_eq!(p.to_string(), "[0.5, .., 'a'..='z', []]");
+    assert_eq!(
+        array(4, [float(f64::NAN..=f64::NAN)], false).to_string(),
+        "[NaN]"
+    );
+
+    let p = just(affixed(Some("http://"), Some(".lm")));
+    assert_eq!(
+        p.display(&SourcePrinter).to_string(),
+        "just (\"http://\" ++ _ ++ \".lm\")"
+    );
+    assert_eq!(
+        string("main").display(&SourcePrinter).to_string(),
+        "\"main\""
+    );
+
+    // constructors print like patterns without their parameters
+    assert_eq!(none().constr.to_string(), "option[1]");
+    assert_eq!(opaque("MAX").constr.to_string(), "\"MAX\"");
+}
+
+#[test]
+fn pattern_metadata() {
+    let mut tree = PatternTree::from_pattern(&tuple([int(0..=5), none()]));
+    let first = tuple([int(1..=1), none()]).with_meta("first");
+    let second = tuple([int(6..=6), none()]).with_meta("second");
+    assert_eq!(
+        tree.include_alternatives(&or([first, second])),
+        Reachability {
+            is_reachable: IsReachable(true),
+            redundant_alternatives: vec![vec![0]],
+            redundant_meta: vec!["first"],
+            covered_by: vec![]
+        }
+    );
+
+    let err = tree.try_include_pattern(&tuple([int(0..=0), string("a").with_meta("text")]));
+    assert_eq!(
+        err,
+        Err(MergeError {
+            path: vec![1],
+            kind: MergeErrorKind::KindMismatch,
+            meta: "text"
+        })
+    );
+    let alternatives = or([none().with_meta("none"), string("a").with_meta("text")]);
+    let err = tree.try_include_alternatives(&tuple([or([int(3..=3), int(0..=0)]), alternatives]));
+    assert_eq!(err.unwrap_err().meta, "text");
+
+    // the metadata doesn't change what a pattern matches
+    assert_eq!(just(int(0..=0)).with_meta("just"), just(int(0..=0)));
+}
+
+// serializes the value to JSON and back, leaking the JSON since the test constructors borrow their
+// strings from it
+#[cfg(feature = "serde")]
+fn round_trip<T: serde::Serialize + serde::Deserialize<'static>>(value: &T) -> T {
+    let json: &'static str = serde_json::to_string(value).unwrap().leak();
+    serde_json::from_str(json).unwrap()
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let patterns = [
+        tuple([
+            int(i64::MIN..=-1),
+            uint(0..=255),
+            chars('a'..='z'),
+            float(-0.5..=1.5),
+        ]),
+        point(vec![
+            ("y", just(wildcard("y"))),
+            ("z", none().with_meta("z")),
+        ]),
+        or([
+            list([none()]),
+            list_rest([none()], [wildcard("_")]),
+            len(3..=usize::MAX),
+        ]),
+        tuple([
+            array(4, [opaque("MAX")], true),
+            string("a"),
+            affixed(Some("b"), Some("c")),
+        ]),
+    ];
+    for p in &patterns {
+        let q = round_trip(p);
+        assert_eq!(&q, p);
+    }
+    assert_eq!(round_trip(&patterns[1]).params[2].meta, "z");
+
+    let mut tree = PatternTree::from_pattern(&tuple([int(0..=5), none(), string("a")]));
+    tree.include_pattern(&tuple([
+        wildcard("_"),
+        just(int(0..=0)),
+        affixed(None, Some("a")),
+    ]));
+    tree.include_pattern(&tuple([int(3..=9), wildcard("_"), wildcard("_")]));
+    let mut copy = round_trip(&tree);
+    assert_eq!(copy.to_string(), tree.to_string());
+    assert_eq!(
+        copy.generate_missing_patterns(),
+        tree.generate_missing_patterns()
+    );
+
+    let missing = tree.minimal_missing_patterns(Some(1));
+    let missing_copy = round_trip(&missing);
+    assert_eq!(missing_copy.patterns, missing.patterns);
+    assert_eq!(missing_copy.more, missing.more);
+
+    // the tree keeps track of its wildcards, so it carries on just like the original
+    let p = tuple([wildcard("_"), wildcard("_"), string("ba")]);
+    assert_eq!(copy.include_pattern(&p), tree.include_pattern(&p));
+    assert_eq!(copy.to_string(), tree.to_string());
+    assert!(copy.is_exhaustive() == tree.is_exhaustive());
+
+    let tree = PatternTree::from_pattern(&or([float(f64::NAN..=f64::NAN), float(0.0..=0.0)]));
+    assert_eq!(round_trip(&tree).to_string(), tree.to_string());
+}
+
+#[test]
+fn dot_export() {
+    let mut tree = PatternTree::from_pattern(&just(int(0..=5)));
+    tree.include_pattern(&wildcard("_"));
+    assert_eq!(
+        tree.to_dot(),
+        r#"digraph {
+    n0 [shape=box, label="SumType(\"option\", 1)"];
+    n1 [shape=diamond, label="i64"];
+    n2 [shape=doublecircle, label="arm 0"];
+    n1 -> n2 [label="0..=5", style=solid];
+    n3 [shape=doublecircle, label="arm 1"];
+    n1 -> n3 [label="-9223372036854775808..=-1", style=solid];
+    n4 [shape=doublecircle, label="arm 1"];
+    n1 -> n4 [label="6..=9223372036854775807", style=solid];
+    n0 -> n1 [label="0", style=solid];
+    n5 [shape=doublecircle, label="arm 1"];
+    n0 -> n5 [label="1", style=solid];
+}
+"#
+    );
+
+    let mut tree =
+        PatternTree::from_pattern(&tuple([affixed(Some("a"), None), list_rest([], [none()])]));
+    tree.include_pattern(&tuple([wildcard("_"), len(0..=0)]));
+    let dot = tree.to_dot();
+    assert!(dot.contains(r#"[shape=component, label="Tuple(2)"]"#));
+    assert!(dot.contains(r#"[shape=hexagon, label="infinite"]"#));
+    assert!(dot.contains(r#"[label="String(\"a\") ++ _", style=solid]"#));
+    assert!(dot.contains(r#"[label="_", style=dashed]"#));
+    assert!(dot.contains(r#"[shape=trapezium, label="List"]"#));
+    assert!(dot.contains(r#"[label="len 1.. (0 + 1)", style=solid]"#));
+}
+
+#[test]
+fn remove_arms() {
+    let mut tree = PatternTree::from_arm(0, &int(0..=5));
+    tree.include_arm(1, &int(3..=9));
+    tree.include_arm(2, &int(7..=12));
+    tree.remove_arm(1);
+    let mut expected = PatternTree::from_arm(0, &int(0..=5));
+    expected.include_arm(2, &int(7..=12));
+    assert_eq!(tree.to_string(), expected.to_string());
+    assert_eq!(tree.arms(), vec![0, 2]);
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        expected.generate_missing_patterns()
+    );
+
+    // the later arms take over what the removed arm covered first
+    let mut tree = PatternTree::from_arm(0, &just(int(0..=5)));
+    assert_eq!(
+        tree.include_arm(1, &just(int(2..=3))).is_reachable,
+        IsReachable(false)
+    );
+    tree.include_arm(2, &wildcard("_"));
+    assert_eq!(tree.arms(), vec![0, 2]);
+    tree.remove_arm(0);
+    assert_eq!(tree.arms(), vec![1, 2]);
+    assert_eq!(tree.arms_matching(&just(int(2..=2))), vec![1]);
+    assert_eq!(tree.arms_matching(&just(int(4..=4))), vec![2]);
+    assert!(tree.is_exhaustive());
+
+    tree.remove_arm(2);
+    let expected = PatternTree::from_arm(1, &just(int(2..=3)));
+    assert_eq!(tree.to_string(), expected.to_string());
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        expected.generate_missing_patterns()
+    );
+
+    // the sum type is kept around, like in a tree made by `PatternTree::empty`
+    tree.remove_arm(1);
+    assert!(tree.arms().is_empty());
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        vec![just(wildcard("_")), none()]
+    );
+    assert_eq!(tree.include_arm(3, &none()).is_reachable, IsReachable(true));
+
+    let mut tree = PatternTree::from_arm(0, &tuple([string("a"), list_rest([none()], [])]));
+    let prefixed = tuple([affixed(Some("a"), None), wildcard("_")]);
+    assert_eq!(
+        tree.include_arm(1, &prefixed).is_reachable,
+        IsReachable(true)
+    );
+    tree.remove_arm(0);
+    let expected = PatternTree::from_arm(1, &prefixed);
+    assert_eq!(
+        tree.generate_missing_patterns(),
+        expected.generate_missing_patterns()
+    );
+    assert_eq!(tree.arms_matching(&tuple([string("a"), list([])])), vec![1]);
+
+    // removing the last arm leaves a tree which nothing has been included into
+    tree.remove_arm(1);
+    assert_eq!(tree.generate_missing_patterns(), vec![wildcard("_")]);
+    assert_eq!(
+        tree.include_arm(2, &int(0..=0)).is_reachable,
+        IsReachable(true)
+    );
+
+    // unreachable arms use up their arm too, so they can be removed on their own
+    let mut tree = PatternTree::from_pattern(&none());
+    assert_reach!(tree, none(), IsReachable(false));
+    assert_reach!(tree, just(wildcard("_")), IsReachable(true));
+    assert_eq!(tree.arms_matching(&just(int(3..=3))), vec![2]);
+    tree.remove_arm(1);
+    assert_eq!(tree.arms(), vec![0, 2]);
+    tree.remove_arm(0);
+    assert!(tree.arms_matching(&none()).is_empty());
+    assert_reach!(tree, none(), IsReachable(true));
+    assert_eq!(tree.arms(), vec![2, 3]);
+}
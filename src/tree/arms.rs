@@ -0,0 +1,243 @@
+use super::merge::{affixes_of, overlaps, region, Side};
+use super::{
+    Arm, Branch, InfiniteBranch, InfiniteValues, Length, Node, OrderedFloat, PatternTree, Tags,
+};
+use crate::{ConstantConstructor, Constructor, Constructors, FlatPatterns, Pattern};
+use itertools::Itertools;
+
+impl<C: Constructors> PatternTree<C> {
+    /// The arms which are still the first to cover at least some value
+    pub fn arms(&self) -> Vec<Arm> {
+        let mut arms = vec![];
+        self.root.for_each_end(&mut |arm| arms.push(arm));
+        arms.sort_unstable();
+        arms.dedup();
+        arms
+    }
+
+    /// The arms which handle the values matched by the pattern
+    ///
+    /// For a pattern without wildcards or ranges this is the single arm the value would be
+    /// dispatched to, or nothing if no arm covers it.
+    pub fn arms_matching(&self, p: &Pattern<C>) -> Vec<Arm> {
+        let mut arms = vec![];
+        for expansion in p.expand_alternatives() {
+            self.root
+                .collect_arms(expansion.pattern.flatten(), &mut arms);
+        }
+        arms.sort_unstable();
+        arms.dedup();
+        arms
+    }
+}
+
+impl<C: Constructors> Node<C> {
+    fn for_each_end(&self, f: &mut impl FnMut(Arm)) {
+        match self {
+            Node::SignedInteger { branches, .. } => for_each_end(branches, f),
+            Node::UnsignedInteger { branches, .. } => for_each_end(branches, f),
+            Node::Char(branches) => for_each_end(branches, f),
+            Node::Float(branches) => for_each_end(branches, f),
+            Node::Variant(_, branches) => for_each_end(branches, f),
+            Node::Lengthed(_, branches) => for_each_end(branches, f),
+            Node::Infinite(wc, branches) => {
+                for_each_end(branches, f);
+                wc.con.iter().for_each(|con| con.for_each_end(f));
+            }
+            Node::Constant(_, con) => con.for_each_end(f),
+            Node::UnknownWildcard(wc) => wc.con.iter().for_each(|con| con.for_each_end(f)),
+            Node::End(arms) => f(arms[0]),
+        }
+    }
+
+    fn collect_arms(&self, mut src: FlatPatterns<C>, arms: &mut Vec<Arm>) {
+        let (constr, params) = match src.pop_front() {
+            None => {
+                if let Node::End(covering) = self {
+                    arms.push(covering[0]);
+                }
+                return;
+            }
+            Some(flat) => flat,
+        };
+
+        match (constr, self) {
+            (Constructor::Wildcard(_), tree) => tree.collect_arms_of_wildcard(src, arms),
+            (Constructor::Variant { tag, .. }, Node::Variant(_, branches)) => branches
+                .iter()
+                .filter(|branch| branch.data == tag)
+                .for_each(|branch| branch.con.collect_arms(src.clone(), arms)),
+            (Constructor::SignedInteger { range, .. }, Node::SignedInteger { branches, .. }) => {
+                branches
+                    .iter()
+                    .filter(|branch| overlaps(&branch.data, &range))
+                    .for_each(|branch| branch.con.collect_arms(src.clone(), arms))
+            }
+            (
+                Constructor::UnsignedInteger { range, .. },
+                Node::UnsignedInteger { branches, .. },
+            ) => branches
+                .iter()
+                .filter(|branch| overlaps(&branch.data, &range))
+                .for_each(|branch| branch.con.collect_arms(src.clone(), arms)),
+            (Constructor::Char { range }, Node::Char(branches)) => branches
+                .iter()
+                .filter(|branch| overlaps(&branch.data, &range))
+                .for_each(|branch| branch.con.collect_arms(src.clone(), arms)),
+            (Constructor::Float { range }, Node::Float(branches)) => {
+                let range = OrderedFloat::range(range);
+                branches
+                    .iter()
+                    .filter(|branch| overlaps(&branch.data, &range))
+                    .for_each(|branch| branch.con.collect_arms(src.clone(), arms))
+            }
+            (
+                constr @ (Constructor::Lenghted(_)
+                | Constructor::LengthedRest { .. }
+                | Constructor::LengthRange { .. }),
+                Node::Lengthed(_, branches),
+            ) => {
+                let length = Length::of(&constr, params).unwrap();
+                branches
+                    .iter()
+                    .filter(|branch| overlaps(&branch.data.lengths, &length.lengths))
+                    .for_each(|branch| {
+                        let src = project(src.clone(), &length, &branch.data);
+                        branch.con.collect_arms(src, arms)
+                    })
+            }
+            (Constructor::Infinite(constr), Node::Infinite(wc, branches)) => {
+                let (prefix, suffix) = affixes_of(branches, &constr);
+                let literal = InfiniteValues::Literal(constr);
+                match branches.iter().find(|branch| branch.data == literal) {
+                    Some(branch) => branch.con.collect_arms(src, arms),
+                    None => {
+                        if let Some(con) = region(branches, wc, prefix, suffix) {
+                            con.collect_arms(src, arms)
+                        }
+                    }
+                }
+            }
+            (Constructor::InfiniteAffix { prefix, suffix }, Node::Infinite(wc, branches)) => {
+                for branch in branches {
+                    if let InfiniteValues::Literal(value) = &branch.data {
+                        if Side::Prefix.includes(value, &prefix)
+                            && Side::Suffix.includes(value, &suffix)
+                        {
+                            branch.con.collect_arms(src.clone(), arms);
+                        }
+                    }
+                }
+
+                let prefixes = overlapping(branches, Side::Prefix, &prefix);
+                let suffixes = overlapping(branches, Side::Suffix, &suffix);
+                for (prefix, suffix) in prefixes.into_iter().cartesian_product(suffixes) {
+                    if let Some(con) = region(branches, wc, prefix, suffix) {
+                        con.collect_arms(src.clone(), arms);
+                    }
+                }
+            }
+            (Constructor::Constant(_), Node::Constant(_, con)) => con.collect_arms(src, arms),
+            (_, Node::UnknownWildcard(wc)) => {
+                if let Some(con) = wc.con.as_deref() {
+                    src.skip_patterns(params);
+                    con.collect_arms(src, arms);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn collect_arms_of_wildcard(&self, src: FlatPatterns<C>, arms: &mut Vec<Arm>) {
+        match self {
+            Node::SignedInteger { branches, .. } => branches
+                .iter()
+                .for_each(|branch| branch.con.collect_arms(src.clone(), arms)),
+            Node::UnsignedInteger { branches, .. } => branches
+                .iter()
+                .for_each(|branch| branch.con.collect_arms(src.clone(), arms)),
+            Node::Char(branches) => branches
+                .iter()
+                .for_each(|branch| branch.con.collect_arms(src.clone(), arms)),
+            Node::Float(branches) => branches
+                .iter()
+                .for_each(|branch| branch.con.collect_arms(src.clone(), arms)),
+            Node::Variant(type_, branches) => branches.iter().for_each(|branch| {
+                branch
+                    .con
+                    .collect_arms(src.clone_to_padded(type_.params_of(branch.data)), arms)
+            }),
+            Node::Lengthed(_, branches) => branches.iter().for_each(|branch| {
+                branch
+                    .con
+                    .collect_arms(src.clone_to_padded(branch.data.params()), arms)
+            }),
+            Node::Infinite(wc, branches) => {
+                for branch in branches {
+                    branch.con.collect_arms(src.clone(), arms);
+                }
+                if let Some(con) = wc.con.as_deref() {
+                    con.collect_arms(src, arms);
+                }
+            }
+            Node::Constant(constr, con) => {
+                con.collect_arms(src.clone_to_padded(constr.len_requirement()), arms)
+            }
+            Node::UnknownWildcard(wc) => {
+                if let Some(con) = wc.con.as_deref() {
+                    con.collect_arms(src, arms);
+                }
+            }
+            Node::End(_) => {}
+        }
+    }
+}
+
+fn for_each_end<C: Constructors, A>(branches: &[Branch<C, A>], f: &mut impl FnMut(Arm)) {
+    branches
+        .iter()
+        .for_each(|branch| branch.con.for_each_end(f))
+}
+
+// the groups of this side with values matched by a pattern with `affix`, which besides the ones it
+// covers entirely may include the group of values it's contained in
+fn overlapping<C: Constructors>(
+    branches: &[InfiniteBranch<C>],
+    side: Side,
+    affix: &Option<C::Infinite>,
+) -> Vec<Option<C::Infinite>> {
+    let affixes = side.affixes(branches);
+    let mut groups = side.covered_by(&affixes, affix);
+    if let Some(affix) = affix {
+        let parent = side.longest(&affixes, affix);
+        if !groups.contains(&parent) {
+            groups.push(parent);
+        }
+    }
+    groups
+}
+
+// rearranges the leading parameters of a lengthed value from the shape of `from` into the shape
+// of `to`. Parameters without a counterpart are dropped since `to` treats them as wildcards, and
+// the missing ones are filled with wildcards.
+fn project<C: Constructors>(
+    mut src: FlatPatterns<C>,
+    from: &Length,
+    to: &Length,
+) -> FlatPatterns<C> {
+    let n = from.single().or_else(|| to.single());
+    let params = (0..from.params())
+        .map(|i| (from.position(i, n), src.pop_pattern()))
+        .collect::<Vec<_>>();
+
+    let mut projected = FlatPatterns::new();
+    for i in 0..to.params() {
+        let position = to.position(i, n);
+        match params.iter().find(|(p, _)| *p == position) {
+            Some((_, param)) => projected.extend(param.iter().cloned()),
+            None => projected.push_back((Constructor::Wildcard(C::Wildcard::default()), 0)),
+        }
+    }
+    projected.extend(src);
+    projected
+}
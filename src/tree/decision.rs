@@ -0,0 +1,231 @@
+use super::{Arm, Branch, InfiniteValues, Node, PatternTree, Tags, WildcardKeeper};
+use crate::{ConstantConstructor, Constructors};
+use itertools::{Either, Itertools};
+use std::ops::RangeInclusive;
+
+/// The parameters leading from the matched value to the value being tested
+pub type Access = Vec<Param>;
+
+/// The prefix and suffix which the values of a case of an infinite switch start and end with
+pub type Affixes<I> = (Option<I>, Option<I>);
+
+/// A step from a value into one of its parameters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Param {
+    Index(usize),
+    /// The parameter this many places from the end, where 1 is the last one
+    ///
+    /// Used for the parameters after the rest of a lengthed value since their index depends on
+    /// its length.
+    FromEnd(usize),
+}
+
+/// An executable decision tree compiled from a `Node`
+///
+/// Every value is tested at most once, and each switch continues with the remaining tests
+/// for the values that are still left to inspect.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decision<C: Constructors> {
+    /// Switch on the tag of a sum type variant
+    ///
+    /// Tags without a case aren't handled by any arm. For a non-exhaustive sum type, the tag after
+    /// `max()` stands for all of its unknown variants.
+    Variant {
+        access: Access,
+        type_: C::SumType,
+        cases: Vec<(u64, Self)>,
+    },
+    /// Switch on which range a signed integer falls into
+    ///
+    /// Numbers outside of the ranges aren't handled by any arm.
+    SignedInteger {
+        access: Access,
+        bitsize: u8,
+        cases: Vec<(RangeInclusive<i128>, Self)>,
+    },
+    /// Switch on which range an unsigned integer falls into
+    ///
+    /// Numbers outside of the ranges aren't handled by any arm.
+    UnsignedInteger {
+        access: Access,
+        bitsize: u8,
+        cases: Vec<(RangeInclusive<u128>, Self)>,
+    },
+    /// Switch on which range a char falls into
+    ///
+    /// Chars outside of the ranges aren't handled by any arm.
+    Char {
+        access: Access,
+        cases: Vec<(RangeInclusive<char>, Self)>,
+    },
+    /// Switch on which range a float falls into, where NaN is matched by `NaN..=NaN`
+    ///
+    /// Floats outside of the ranges aren't handled by any arm.
+    Float {
+        access: Access,
+        cases: Vec<(RangeInclusive<f64>, Self)>,
+    },
+    /// Switch on which range the amount of parameters of a lengthed value falls into
+    ///
+    /// Lengths outside of the ranges aren't handled by any arm.
+    Length {
+        access: Access,
+        constr: C::Lengthed,
+        cases: Vec<(RangeInclusive<usize>, Self)>,
+    },
+    /// Switch on equality with literals of an infinite type, and otherwise on the longest of the
+    /// prefixes and suffixes in `affixes` which the value starts and ends with
+    ///
+    /// The fallback is taken if the value has neither, while the other combinations without a case
+    /// aren't handled by any arm.
+    Infinite {
+        access: Access,
+        cases: Vec<(C::Infinite, Self)>,
+        affixes: Vec<(Affixes<C::Infinite>, Self)>,
+        fallback: Box<Self>,
+    },
+    /// The value is handled by this arm
+    Arm(Arm),
+    /// No arm handles the value, which means the match wasn't exhaustive
+    Fail,
+}
+
+impl<C: Constructors> PatternTree<C> {
+    /// Compiles the tree into a decision tree which tests each value at most once
+    pub fn compile(&self) -> Decision<C> {
+        self.root.to_decision(vec![vec![]])
+    }
+}
+
+impl<C: Constructors> Node<C> {
+    // `pending` is a stack of the values which are yet to be tested, with the next on top
+    fn to_decision(&self, mut pending: Vec<Access>) -> Decision<C> {
+        if let Node::End(arms) = self {
+            debug_assert!(pending.is_empty());
+            return Decision::Arm(arms[0]);
+        }
+
+        let access = pending
+            .pop()
+            .expect("Node continues past the matched value");
+
+        match self {
+            Node::Variant(type_, branches) => {
+                let mut cases = cases(branches, &access, &pending, |tag| {
+                    indices(type_.params_of(*tag))
+                });
+                cases.sort_by_key(|(tag, _)| *tag);
+                Decision::Variant {
+                    access,
+                    type_: type_.clone(),
+                    cases,
+                }
+            }
+            Node::SignedInteger { bitsize, branches } => {
+                let mut cases = cases(branches, &access, &pending, |_| vec![]);
+                cases.sort_by_key(|(range, _)| *range.start());
+                Decision::SignedInteger {
+                    access,
+                    bitsize: *bitsize,
+                    cases,
+                }
+            }
+            Node::UnsignedInteger { bitsize, branches } => {
+                let mut cases = cases(branches, &access, &pending, |_| vec![]);
+                cases.sort_by_key(|(range, _)| *range.start());
+                Decision::UnsignedInteger {
+                    access,
+                    bitsize: *bitsize,
+                    cases,
+                }
+            }
+            Node::Char(branches) => {
+                let mut cases = cases(branches, &access, &pending, |_| vec![]);
+                cases.sort_by_key(|(range, _)| *range.start());
+                Decision::Char { access, cases }
+            }
+            Node::Float(branches) => {
+                let mut cases = cases(branches, &access, &pending, |_| vec![]);
+                cases.sort_by_key(|(range, _)| *range.start());
+                let cases = cases
+                    .into_iter()
+                    .map(|(range, decision)| {
+                        (range.start().to_f64()..=range.end().to_f64(), decision)
+                    })
+                    .collect();
+                Decision::Float { access, cases }
+            }
+            Node::Lengthed(constr, branches) => {
+                let cases = cases(branches, &access, &pending, |length| {
+                    let suffix = (1..=length.suffix).rev().map(Param::FromEnd);
+                    (0..length.prefix).map(Param::Index).chain(suffix).collect()
+                });
+                Decision::Length {
+                    cases: cases
+                        .into_iter()
+                        .map(|(length, decision)| (length.lengths, decision))
+                        .collect(),
+                    access,
+                    constr: constr.clone(),
+                }
+            }
+            Node::Infinite(wc, branches) => {
+                let (cases, affixes) = cases(branches, &access, &pending, |_| vec![])
+                    .into_iter()
+                    .partition_map(|(values, decision)| match values {
+                        InfiniteValues::Literal(value) => Either::Left((value, decision)),
+                        InfiniteValues::Affixed { prefix, suffix } => {
+                            Either::Right(((prefix, suffix), decision))
+                        }
+                    });
+                Decision::Infinite {
+                    access,
+                    cases,
+                    affixes,
+                    fallback: Box::new(fallback(wc, pending)),
+                }
+            }
+            Node::Constant(constr, con) => {
+                push_params(&mut pending, &access, indices(constr.len_requirement()));
+                con.to_decision(pending)
+            }
+            Node::UnknownWildcard(wc) => fallback(wc, pending),
+            Node::End(_) => unreachable!(),
+        }
+    }
+}
+
+fn cases<C: Constructors, A: Clone>(
+    branches: &[Branch<C, A>],
+    access: &Access,
+    pending: &[Access],
+    params_of: impl Fn(&A) -> Vec<Param>,
+) -> Vec<(A, Decision<C>)> {
+    branches
+        .iter()
+        .map(|Branch { data, con }| {
+            let mut pending = pending.to_vec();
+            push_params(&mut pending, access, params_of(data));
+            (data.clone(), con.to_decision(pending))
+        })
+        .collect()
+}
+
+fn fallback<C: Constructors>(wc: &WildcardKeeper<C>, pending: Vec<Access>) -> Decision<C> {
+    match wc.con.as_deref() {
+        Some(con) => con.to_decision(pending),
+        None => Decision::Fail,
+    }
+}
+
+fn push_params(pending: &mut Vec<Access>, access: &Access, params: Vec<Param>) {
+    for step in params.into_iter().rev() {
+        let mut param = access.clone();
+        param.push(step);
+        pending.push(param);
+    }
+}
+
+fn indices(params: usize) -> Vec<Param> {
+    (0..params).map(Param::Index).collect()
+}
@@ -0,0 +1,135 @@
+use super::{Branch, InfiniteValues, Length, Node, PatternTree, WildcardKeeper};
+use crate::Constructors;
+use std::fmt::Debug;
+
+impl<C: Constructors> PatternTree<C> {
+    /// Renders the tree as a graph in the DOT language of Graphviz
+    ///
+    /// Sum types are drawn as boxes, integer, char and float ranges as diamonds, lengthed values
+    /// as trapeziums, constants as components, infinite values as hexagons, unknown wildcards as
+    /// circles and the arms at the ends as double circles. Edges are labelled by the tag, range,
+    /// lengths or values of their branch, while the continuations of wildcards are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = Dot {
+            out: String::from("digraph {\n"),
+            nodes: 0,
+        };
+        dot.node(&self.root);
+        dot.out.push_str("}\n");
+        dot.out
+    }
+}
+
+struct Dot {
+    out: String,
+    nodes: usize,
+}
+
+impl Dot {
+    // writes the node of the tree followed by the nodes after it, returning its id
+    fn node<C: Constructors>(&mut self, tree: &Node<C>) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+
+        let (shape, label) = match tree {
+            Node::SignedInteger { bitsize, .. } => ("diamond", format!("i{}", bitsize)),
+            Node::UnsignedInteger { bitsize, .. } => ("diamond", format!("u{}", bitsize)),
+            Node::Char(_) => ("diamond", "char".to_string()),
+            Node::Float(_) => ("diamond", "float".to_string()),
+            Node::Variant(type_, _) => ("box", format!("{:?}", type_)),
+            Node::Lengthed(constr, _) => ("trapezium", format!("{:?}", constr)),
+            Node::Constant(constr, _) => ("component", format!("{:?}", constr)),
+            Node::Infinite(..) => ("hexagon", "infinite".to_string()),
+            Node::UnknownWildcard(_) => ("circle", "_".to_string()),
+            Node::End(arms) => ("doublecircle", format!("arm {}", arms[0])),
+        };
+        let node = format!(
+            "    n{} [shape={}, label=\"{}\"];\n",
+            id,
+            shape,
+            escape(&label)
+        );
+        self.out.push_str(&node);
+
+        match tree {
+            Node::SignedInteger { branches, .. } => self.branches(id, branches, debug),
+            Node::UnsignedInteger { branches, .. } => self.branches(id, branches, debug),
+            Node::Char(branches) => self.branches(id, branches, debug),
+            Node::Float(branches) => self.branches(id, branches, debug),
+            Node::Variant(_, branches) => self.branches(id, branches, u64::to_string),
+            Node::Lengthed(_, branches) => self.branches(id, branches, length),
+            Node::Constant(_, con) => self.edge(id, con, "", "solid"),
+            Node::Infinite(wc, branches) => {
+                self.branches(id, branches, infinite);
+                self.wildcard(id, wc);
+            }
+            Node::UnknownWildcard(wc) => self.wildcard(id, wc),
+            Node::End(_) => {}
+        }
+
+        id
+    }
+
+    fn branches<C: Constructors, A>(
+        &mut self,
+        from: usize,
+        branches: &[Branch<C, A>],
+        label: impl Fn(&A) -> String,
+    ) {
+        for Branch { data, con } in branches {
+            self.edge(from, con, &label(data), "solid");
+        }
+    }
+
+    fn wildcard<C: Constructors>(&mut self, from: usize, wc: &WildcardKeeper<C>) {
+        if let Some(con) = wc.con.as_deref() {
+            self.edge(from, con, "_", "dashed");
+        }
+    }
+
+    fn edge<C: Constructors>(&mut self, from: usize, to: &Node<C>, label: &str, style: &str) {
+        let (to, label) = (self.node(to), escape(label));
+        let edge = format!(
+            "    n{} -> n{} [label=\"{}\", style={}];\n",
+            from, to, label, style
+        );
+        self.out.push_str(&edge);
+    }
+}
+
+fn debug(data: &impl Debug) -> String {
+    format!("{:?}", data)
+}
+
+fn length(length: &Length) -> String {
+    let (start, end) = (length.lengths.start(), length.lengths.end());
+    let lengths = match (start, end) {
+        _ if start == end => format!("len {}", start),
+        (_, &usize::MAX) => format!("len {}..", start),
+        _ => format!("len {}..={}", start, end),
+    };
+    if length.suffix == 0 {
+        lengths
+    } else {
+        format!("{} ({} + {})", lengths, length.prefix, length.suffix)
+    }
+}
+
+fn infinite<I: Debug>(values: &InfiniteValues<I>) -> String {
+    match values {
+        InfiniteValues::Literal(value) => format!("{:?}", value),
+        InfiniteValues::Affixed { prefix, suffix } => {
+            let prefix = prefix.as_ref().map(|prefix| format!("{:?} ++ ", prefix));
+            let suffix = suffix.as_ref().map(|suffix| format!(" ++ {:?}", suffix));
+            format!(
+                "{}_{}",
+                prefix.unwrap_or_default(),
+                suffix.unwrap_or_default()
+            )
+        }
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -1,131 +1,260 @@
 use super::*;
-use crate::pattern::{ConstantConstructor, SumtypeConstructor};
+use crate::pattern::{ConstantConstructor, InfiniteConstructor, SumtypeConstructor};
 use std::ops::RangeInclusive;
 
 pub(super) struct Merge<'t, C: Constructors> {
-    dst: &'t mut PatternTree<C>,
+    dst: &'t mut Node<C>,
     src: FlatPatterns<C>,
 }
 
 impl<'t, C: Constructors> Merge<'t, C> {
-    pub fn new(src: FlatPatterns<C>, dst: &'t mut PatternTree<C>) -> Self {
+    pub fn new(src: FlatPatterns<C>, dst: &'t mut Node<C>) -> Self {
         Self { dst, src }
     }
 
-    pub fn run(mut self) -> IsReachable {
-        match self.src.pop_front() {
-            None => IsReachable(false),
-            Some((constr, params)) => match (constr, self.dst) {
-                (Constructor::Variant { type_, tag }, PatternTree::Variant(_, branches)) => {
-                    self.src.into_merger(branches).with_variant(&type_, tag)
+    pub fn run(mut self) -> MergeResult<C::Meta> {
+        let (constr, params) = match self.src.pop_front() {
+            // the arm is remembered so it can take over if the earlier arms are removed
+            None if self.dst.is_end() => {
+                if let Node::End(arms) = self.dst {
+                    if !arms.contains(&self.src.arm()) {
+                        arms.push(self.src.arm());
+                    }
                 }
+                return Ok(IsReachable(false));
+            }
+            None if self.dst.is_uncovered() => {
+                *self.dst = Node::End(vec![self.src.arm()]);
+                return Ok(IsReachable(true));
+            }
+            None => return Err(self.src.mismatch(MergeErrorKind::ArityMismatch)),
+            Some(flat) => flat,
+        };
+
+        match (constr, self.dst) {
+            (Constructor::Or, _) => unreachable!("or-patterns are expanded before being merged"),
+            (Constructor::Opaque(_), _) => {
+                unreachable!("opaque constants are replaced before being merged")
+            }
 
-                (Constructor::Lenghted(constr), PatternTree::Lengthed(_, wc, branches)) => self
-                    .src
-                    .into_merger(branches)
-                    .with_constant(constr, wc, params),
-
-                (
-                    Constructor::SignedInteger { range, bitsize: bs },
-                    PatternTree::SignedInteger { branches, bitsize },
-                ) => {
-                    assert_eq!(params, 0);
-                    assert_eq!(bs, *bitsize, "inconsistent bitsize of range patterns");
-                    self.src.into_merger(branches).with_range(range)
+            (Constructor::Variant { type_, tag }, Node::Variant(etype, branches)) => {
+                if type_ != *etype {
+                    return Err(self.src.mismatch(MergeErrorKind::SumTypeMismatch));
+                }
+                if params != type_.params_for(tag) {
+                    return Err(self.src.mismatch(MergeErrorKind::ArityMismatch));
+                }
+                self.src.into_merger(branches).with_variant(&type_, tag)
+            }
+
+            (
+                constr @ (Constructor::Lenghted(_)
+                | Constructor::LengthedRest { .. }
+                | Constructor::LengthRange { .. }),
+                Node::Lengthed(_, branches),
+            ) => {
+                let length = Length::of(&constr, params).unwrap();
+                self.src.into_merger(branches).with_length(length)
+            }
+
+            (
+                Constructor::SignedInteger { range, bitsize: bs },
+                Node::SignedInteger { branches, bitsize },
+            ) => {
+                self.src.check_range(params, bs, *bitsize)?;
+                self.src.into_merger(branches).with_range(range)
+            }
+
+            (
+                Constructor::UnsignedInteger { range, bitsize: bs },
+                Node::UnsignedInteger { branches, bitsize },
+            ) => {
+                self.src.check_range(params, bs, *bitsize)?;
+                self.src.into_merger(branches).with_range(range)
+            }
+
+            (Constructor::Char { range }, Node::Char(branches)) => {
+                if params != 0 {
+                    return Err(self.src.mismatch(MergeErrorKind::ArityMismatch));
                 }
+                self.src.into_merger(branches).with_range(range)
+            }
 
-                (Constructor::Constant(constr), PatternTree::Constant(econstr, con)) => {
-                    assert_eq!(constr.len_requirement(), econstr.len_requirement());
-                    self.src.merge_with(con)
+            (Constructor::Float { range }, Node::Float(branches)) => {
+                if params != 0 {
+                    return Err(self.src.mismatch(MergeErrorKind::ArityMismatch));
                 }
+                self.src
+                    .into_merger(branches)
+                    .with_range(OrderedFloat::range(range))
+            }
 
-                (Constructor::Infinite(constr), PatternTree::Infinite(wc, branches)) => {
-                    self.src.into_merger(branches).with_infinite(constr, wc)
+            (Constructor::Constant(constr), Node::Constant(econstr, con)) => {
+                if constr.len_requirement() != econstr.len_requirement() {
+                    return Err(self.src.mismatch(MergeErrorKind::ArityMismatch));
                 }
+                self.src.merge_with(Arc::make_mut(con))
+            }
+
+            (Constructor::Infinite(constr), Node::Infinite(wc, branches)) => {
+                self.src.into_merger(branches).with_infinite(constr, wc)
+            }
+
+            (Constructor::InfiniteAffix { prefix, suffix }, Node::Infinite(wc, branches)) => {
+                if params != 0 {
+                    return Err(self.src.mismatch(MergeErrorKind::ArityMismatch));
+                }
+                let wildcard = C::Wildcard::default();
+                self.src
+                    .into_merger(branches)
+                    .with_affix(prefix, suffix, wc, wildcard)
+            }
 
-                (Constructor::Wildcard(wc), dst) => match dst {
-                    PatternTree::SignedInteger { branches, bitsize } => self
-                        .src
-                        .into_merger(branches)
-                        .with_wildcard_signed_integer(wc, *bitsize as u32),
-                    PatternTree::Variant(constr, branches) => self
-                        .src
-                        .into_merger(branches)
-                        .with_wildcard_variant(constr, wc),
-                    PatternTree::Infinite(wildcard, branches) => self
-                        .src
-                        .into_merger(branches)
-                        .with_wildcard_infinite(wildcard, wc),
-                    PatternTree::Lengthed(_, wildcard, branches) => self
-                        .src
-                        .into_merger(branches)
-                        .with_wildcard_lengthed(wildcard, wc),
-                    PatternTree::UnknownWildcard(existing) => existing.with_wildcard(wc, self.src),
-                    PatternTree::Constant(constr, con) => {
-                        let fillers =
-                            std::iter::repeat((Constructor::Wildcard(C::Wildcard::default()), 0))
-                                .take(constr.len_requirement());
-
-                        for filler in fillers {
-                            self.src.push_front(filler);
-                        }
-
-                        self.src.merge_with(con)
+            (Constructor::Wildcard(wc), dst) => match dst {
+                Node::SignedInteger { branches, bitsize } => self
+                    .src
+                    .into_merger(branches)
+                    .with_wildcard_signed_integer(wc, *bitsize as u32),
+                Node::UnsignedInteger { branches, bitsize } => self
+                    .src
+                    .into_merger(branches)
+                    .with_wildcard_unsigned_integer(wc, *bitsize as u32),
+                Node::Char(branches) => self.src.into_merger(branches).with_range(CHARS),
+                Node::Float(branches) => {
+                    let mut is_reachable = IsReachable(false);
+                    for range in OrderedFloat::contiguous(OrderedFloat::all()) {
+                        is_reachable |= self.src.clone().into_merger(branches).with_range(range)?;
                     }
-                    _ => todo!(),
-                },
-
-                (constr, r @ PatternTree::UnknownWildcard(_)) => {
-                    take_mut::take(r, |dst| match dst {
-                        PatternTree::UnknownWildcard(keeper) => {
-                            Self::init_from_wc(constr.clone(), &self.src, keeper)
-                        }
-                        _ => unreachable!(),
-                    });
-                    self.src.push_front((constr.clone(), params));
-                    self.src.merge_with(r)
+                    Ok(is_reachable)
                 }
+                Node::Variant(constr, branches) => self
+                    .src
+                    .into_merger(branches)
+                    .with_wildcard_variant(constr, wc),
+                Node::Infinite(wildcard, branches) => self
+                    .src
+                    .into_merger(branches)
+                    .with_affix(None, None, wildcard, wc),
+                Node::Lengthed(constr, branches) => self
+                    .src
+                    .into_merger(branches)
+                    .with_length(Length::any(constr)),
+                Node::UnknownWildcard(existing) => existing.with_wildcard(wc, self.src),
+                Node::Constant(constr, con) => {
+                    let fillers = std::iter::repeat_n(
+                        (Constructor::Wildcard(C::Wildcard::default()), 0),
+                        constr.len_requirement(),
+                    );
+
+                    for filler in fillers {
+                        self.src.push_front(filler);
+                    }
 
-                _ => todo!(),
+                    self.src.merge_with(Arc::make_mut(con))
+                }
+                Node::End(_) => Err(self.src.mismatch(MergeErrorKind::ArityMismatch)),
             },
+
+            (constr, r @ Node::UnknownWildcard(_)) => {
+                take_mut::take(r, |dst| match dst {
+                    Node::UnknownWildcard(keeper) => {
+                        Self::init_from_wc(constr.clone(), &self.src, keeper)
+                    }
+                    _ => unreachable!(),
+                });
+                self.src.push_front((constr.clone(), params));
+                self.src.merge_with(r)
+            }
+
+            (_, Node::End(_)) => Err(self.src.mismatch(MergeErrorKind::ArityMismatch)),
+            _ => Err(self.src.mismatch(MergeErrorKind::KindMismatch)),
         }
     }
 
+    // an empty keeper means nothing was covered yet, in which case the new node is empty as well
     fn init_from_wc(
         constr: Constructor<C>,
         src: &FlatPatterns<C>,
         mut keeper: WildcardKeeper<C>,
-    ) -> PatternTree<C> {
+    ) -> Node<C> {
+        if let Constructor::Wildcard(wc) = constr {
+            keeper.buf.push((wc, src.clone()));
+            return Node::UnknownWildcard(keeper);
+        }
+        if let Constructor::Infinite(_) | Constructor::InfiniteAffix { .. } = constr {
+            return Node::Infinite(keeper, vec![]);
+        }
+
+        let con = keeper.con.map(Arc::unwrap_or_clone);
+
         match constr {
-            Constructor::Wildcard(wc) => {
-                keeper.buf.push((wc, src.clone()));
-                PatternTree::UnknownWildcard(keeper)
+            Constructor::Lenghted(constr)
+            | Constructor::LengthedRest { constr, .. }
+            | Constructor::LengthRange { constr, .. } => {
+                let length = Length::any(&constr);
+                Node::Lengthed(constr, covering(&con, length))
             }
-            Constructor::Lenghted(constr) => PatternTree::Lengthed(constr, keeper, vec![]),
             Constructor::Constant(constr) => {
-                let params =
-                    std::iter::repeat(Pattern::new(Constructor::Wildcard(C::Wildcard::default())))
-                        .take(constr.len_requirement())
-                        .collect();
-                Pattern::new(Constructor::Constant(constr))
-                    .with_params(params)
-                    .flatten()
-                    .drain_to_patterntree()
+                let params = match con {
+                    Some(con) => Node::prefixed_by_wildcards(constr.len_requirement(), con),
+                    None => Node::UnknownWildcard(WildcardKeeper::new()),
+                };
+                Node::Constant(constr, Arc::new(params))
+            }
+            Constructor::Variant { type_, .. } => {
+                let branches = type_
+                    .tags()
+                    .into_iter()
+                    .flat_map(|tag| {
+                        let params = type_.params_of(tag);
+                        con.iter().map(move |con| Branch {
+                            data: tag,
+                            con: Arc::new(Node::prefixed_by_wildcards(params, con.clone())),
+                        })
+                    })
+                    .collect();
+                Node::Variant(type_, branches)
             }
-            Constructor::Infinite(_) => PatternTree::Infinite(keeper, vec![]),
-            Constructor::Variant { type_, .. } => PatternTree::Variant(type_, vec![]),
-            Constructor::SignedInteger { bitsize, .. } => PatternTree::SignedInteger {
+            Constructor::SignedInteger { bitsize, .. } => Node::SignedInteger {
                 bitsize,
-                branches: vec![Branch {
-                    data: signed_min(bitsize as u32)..=signed_max(bitsize as u32),
-                    con: *keeper.con.unwrap(),
-                }],
+                branches: covering(
+                    &con,
+                    signed_min(bitsize as u32)..=signed_max(bitsize as u32),
+                ),
             },
-            Constructor::UnsignedInteger { .. } => todo!("unsigned integers"),
+            Constructor::UnsignedInteger { bitsize, .. } => Node::UnsignedInteger {
+                bitsize,
+                branches: covering(&con, 0..=unsigned_max(bitsize as u32)),
+            },
+            Constructor::Char { .. } => Node::Char(covering(&con, CHARS)),
+            Constructor::Float { .. } => Node::Float(
+                OrderedFloat::contiguous(OrderedFloat::all())
+                    .into_iter()
+                    .flat_map(|range| covering(&con, range))
+                    .collect(),
+            ),
+            Constructor::Wildcard(_)
+            | Constructor::Infinite(_)
+            | Constructor::InfiniteAffix { .. } => unreachable!(),
+            Constructor::Or => unreachable!("or-patterns are expanded before being merged"),
+            Constructor::Opaque(_) => {
+                unreachable!("opaque constants are replaced before being merged")
+            }
         }
     }
 }
 
+// a single branch covering `data` if anything was covered
+fn covering<C: Constructors, A>(con: &Option<Node<C>>, data: A) -> Vec<Branch<C, A>> {
+    match con {
+        Some(con) => vec![Branch {
+            data,
+            con: Arc::new(con.clone()),
+        }],
+        None => vec![],
+    }
+}
+
 struct Merger<'t, C: Constructors, B> {
     branches: &'t mut Vec<B>,
     ptr: usize,
@@ -134,67 +263,108 @@ struct Merger<'t, C: Constructors, B> {
 
 impl<C: Constructors> FlatPatterns<C> {
     fn into_merger<B>(self, branches: &mut Vec<B>) -> Merger<'_, C, B> {
-        Merger { src: self, branches, ptr: 0 }
+        Merger {
+            src: self,
+            branches,
+            ptr: 0,
+        }
     }
 }
 
 impl<'t, C: Constructors> Merger<'t, C, LengthedBranch<C>> {
-    fn with_constant(
-        self,
-        _: C::Lengthed,
-        wc: &mut WildcardKeeper<C>,
-        params: Params,
-    ) -> IsReachable {
-        let (is_reachable, to_push) = wc.with_branch(
-            self.branches
-                .iter_mut()
-                .find(|Branch { data: eparams, .. }| *eparams == params)
-                .map(|Branch { con, .. }| con),
-            self.src,
-        );
-        if let Some(con) = to_push {
-            self.branches.push(Branch { data: params, con });
+    fn with_length(self, length: Length) -> MergeResult<C::Meta> {
+        split_lengths(self.branches, *length.lengths.start());
+        if let Some(after) = length.lengths.end().checked_add(1) {
+            split_lengths(self.branches, after);
         }
-        is_reachable
-    }
 
-    fn with_wildcard_lengthed(
-        self,
-        existing: &mut WildcardKeeper<C>,
-        wc: C::Wildcard,
-    ) -> IsReachable {
-        for Branch { data: params, con: econ } in self.branches.iter_mut() {
-            econ.on_continuation(*params, &mut |con| {
-                self.src.clone().merge_with(con);
-            });
+        let (inside, outside): (Vec<_>, Vec<_>) = std::mem::take(self.branches)
+            .into_iter()
+            .partition(|branch| overlaps(&branch.data.lengths, &length.lengths));
+        *self.branches = outside;
+
+        let covered = inside
+            .iter()
+            .map(|branch| branch.data.lengths.clone())
+            .collect();
+        let mut is_reachable = IsReachable(false);
+
+        // the existing branches and the pattern are rearranged into a shape they both fit in
+        for Branch { data, con } in inside {
+            for shape in data.common_shapes(&length) {
+                let mut con = con.clone();
+                Arc::make_mut(&mut con).reshape(&data, &shape);
+
+                let mut src = self.src.clone();
+                src.insert_wildcards(length.prefix, shape.params() - length.params());
+                is_reachable |= src.merge_with(Arc::make_mut(&mut con))?;
+
+                self.branches.push(Branch { data: shape, con });
+            }
         }
-        existing.with_wildcard(wc, self.src)
+
+        for lengths in uncovered(covered, length.lengths.clone()) {
+            let data = Length { lengths, ..length };
+            let con = Arc::new(self.src.clone().drain_to_patterntree());
+            self.branches.push(Branch { data, con });
+            is_reachable = IsReachable(true);
+        }
+
+        self.branches
+            .sort_by_key(|branch| *branch.data.lengths.start());
+        Ok(is_reachable)
     }
 }
 
+// splits the branch covering both `at` and the length before it into two
+fn split_lengths<C: Constructors>(branches: &mut Vec<LengthedBranch<C>>, at: usize) {
+    let found = branches.iter_mut().find(|branch| {
+        let lengths = &branch.data.lengths;
+        *lengths.start() < at && at <= *lengths.end()
+    });
+
+    if let Some(Branch { data, con }) = found {
+        let right = Length {
+            lengths: at..=*data.lengths.end(),
+            ..data.clone()
+        };
+        data.lengths = *data.lengths.start()..=at - 1;
+        let con = con.clone();
+        branches.push(Branch { data: right, con });
+    }
+}
+
+pub(crate) fn overlaps<N: Ord>(a: &RangeInclusive<N>, b: &RangeInclusive<N>) -> bool {
+    a.start() <= b.end() && b.start() <= a.end()
+}
+
 impl<'t, C: Constructors> Merger<'t, C, VariantBranch<C>> {
-    fn with_variant(mut self, _: &C::SumType, tag: u64) -> IsReachable {
+    fn with_variant(mut self, type_: &C::SumType, tag: u64) -> MergeResult<C::Meta> {
+        if !type_.is_inhabited(tag) {
+            return Ok(IsReachable(false));
+        }
+
         match self
             .branches
             .iter_mut()
             .find(|Branch { data: etag, .. }| *etag == tag)
         {
-            Some(Branch { con: econ, .. }) => self.src.merge_with(econ),
+            Some(Branch { con: econ, .. }) => self.src.merge_with(Arc::make_mut(econ)),
             None => {
                 self.branches.push(Branch {
                     data: tag,
-                    con: self.src.drain_to_patterntree(),
+                    con: Arc::new(self.src.drain_to_patterntree()),
                 });
-                IsReachable(true)
+                Ok(IsReachable(true))
             }
         }
     }
 
-    fn with_wildcard_variant(self, constr: &C::SumType, _: C::Wildcard) -> IsReachable {
+    fn with_wildcard_variant(self, constr: &C::SumType, _: C::Wildcard) -> MergeResult<C::Meta> {
         let mut is_reachable = IsReachable(false);
 
-        for tag in 0..=constr.max() {
-            let params = constr.params_for(tag);
+        for tag in constr.tags() {
+            let params = constr.params_of(tag);
 
             match self
                 .branches
@@ -202,75 +372,382 @@ impl<'t, C: Constructors> Merger<'t, C, VariantBranch<C>> {
                 .find(|Branch { data: etag, .. }| *etag == tag)
             {
                 None => {
-                    let con = self.src.clone_to_padded(params).drain_to_patterntree();
+                    let con = Arc::new(self.src.clone_to_padded(params).drain_to_patterntree());
                     self.branches.push(Branch { data: tag, con });
                     is_reachable = IsReachable(true);
                 }
                 Some(Branch { con: econ, .. }) => {
-                    is_reachable |= self.src.clone_to_padded(params).merge_with(econ);
+                    let econ = Arc::make_mut(econ);
+                    is_reachable |= self.src.clone_to_padded(params).merge_with(econ)?;
                 }
             }
         }
 
-        is_reachable
+        Ok(is_reachable)
     }
 }
 
 impl<'t, C: Constructors> Merger<'t, C, InfiniteBranch<C>> {
-    fn with_infinite(self, constr: C::Infinite, wc: &WildcardKeeper<C>) -> IsReachable {
-        let matching: Option<_> = self.branches.get_matching(&constr);
-        let (is_reachable, to_push) = wc.with_branch(matching, self.src);
-        if let Some(con) = to_push {
-            self.branches.push(Branch { data: constr.clone(), con });
+    fn with_infinite(
+        mut self,
+        constr: C::Infinite,
+        wc: &WildcardKeeper<C>,
+    ) -> MergeResult<C::Meta> {
+        let (prefix, suffix) = affixes_of(self.branches, &constr);
+        let literal = InfiniteValues::Literal(constr);
+        if let Some(econ) = self.branches.get_matching(&literal) {
+            return self.src.merge_with(econ);
+        }
+
+        // a new literal starts out with what already covers the values it was grouped with
+        match region(self.branches, wc, prefix, suffix).cloned() {
+            None => {
+                let con = Arc::new(self.src.drain_to_patterntree());
+                self.branches.push(Branch { data: literal, con });
+                Ok(IsReachable(true))
+            }
+            Some(mut con) => {
+                let is_reachable = self.src.merge_with(Arc::make_mut(&mut con))?;
+                self.branches.push(Branch { data: literal, con });
+                Ok(is_reachable)
+            }
         }
-        is_reachable
     }
 
-    fn with_wildcard_infinite(
+    // a wildcard is the same as an affix pattern with neither a prefix nor a suffix
+    fn with_affix(
         self,
+        prefix: Option<C::Infinite>,
+        suffix: Option<C::Infinite>,
         existing: &mut WildcardKeeper<C>,
         wc: C::Wildcard,
-    ) -> IsReachable {
-        for Branch { con: econ, .. } in self.branches.iter_mut() {
-            let params = 0;
-            // TODO: we're gonna re-add support for this, there's no reason not to
-            // TODO: this already *is* continuation. So; are we sure we want to call
-            // on_continuation?
-            //
-            // oh ye this code assumes there can be params but we give it params = 0
-            //
-            // and this isn't where the bug is because the bug is actually at Constant
-            econ.on_continuation(params, &mut |con| {
-                self.src.clone().merge_with(con);
-            });
+    ) -> MergeResult<C::Meta> {
+        if let Some(prefix) = &prefix {
+            refine(self.branches, existing, Side::Prefix, prefix);
+        }
+        if let Some(suffix) = &suffix {
+            refine(self.branches, existing, Side::Suffix, suffix);
         }
-        existing.with_wildcard(wc, self.src)
+
+        let mut is_reachable = IsReachable(false);
+
+        for Branch { data, con } in self.branches.iter_mut() {
+            if let InfiniteValues::Literal(value) = data {
+                if Side::Prefix.includes(value, &prefix) && Side::Suffix.includes(value, &suffix) {
+                    is_reachable |= self.src.clone().merge_with(Arc::make_mut(con))?;
+                }
+            }
+        }
+
+        // the pattern's own affixes aren't grouped by yet if nothing covered their values before
+        let covered_by = |side: Side, affix: &Option<C::Infinite>| {
+            let mut affixes = side.affixes(self.branches);
+            if let Some(affix) = affix.as_ref().filter(|affix| !affixes.contains(affix)) {
+                affixes.push(affix.clone());
+            }
+            side.covered_by(&affixes, affix)
+        };
+        let prefixes = covered_by(Side::Prefix, &prefix);
+        let suffixes = covered_by(Side::Suffix, &suffix);
+
+        for (prefix, suffix) in prefixes.into_iter().cartesian_product(suffixes) {
+            if prefix.is_none() && suffix.is_none() {
+                is_reachable |= existing.with_wildcard(wc.clone(), self.src.clone())?;
+                continue;
+            }
+
+            let data = InfiniteValues::Affixed { prefix, suffix };
+            match self.branches.get_matching(&data) {
+                Some(econ) => is_reachable |= self.src.clone().merge_with(econ)?,
+                None => {
+                    let con = Arc::new(self.src.clone().drain_to_patterntree());
+                    self.branches.push(Branch { data, con });
+                    is_reachable = IsReachable(true);
+                }
+            }
+        }
+
+        Ok(is_reachable)
     }
 }
 
+// gives the values starting or ending with `affix` a group of their own, which starts out with
+// what covered them before
+fn refine<C: Constructors>(
+    branches: &mut Vec<InfiniteBranch<C>>,
+    wc: &WildcardKeeper<C>,
+    side: Side,
+    affix: &C::Infinite,
+) {
+    let affixes = side.affixes(branches);
+    if affixes.contains(affix) {
+        return;
+    }
+
+    let parent = side.longest(&affixes, affix);
+    let others = side.other().affixes(branches);
+
+    for other in std::iter::once(None).chain(others.into_iter().map(Some)) {
+        let (prefix, suffix) = side.order(parent.clone(), other.clone());
+        if let Some(con) = region(branches, wc, prefix, suffix).cloned() {
+            let (prefix, suffix) = side.order(Some(affix.clone()), other);
+            let data = InfiniteValues::Affixed { prefix, suffix };
+            branches.push(Branch { data, con });
+        }
+    }
+}
+
+// the continuation covering the values grouped by `prefix` and `suffix`
+pub(crate) fn region<'a, C: Constructors>(
+    branches: &'a [InfiniteBranch<C>],
+    wc: &'a WildcardKeeper<C>,
+    prefix: Option<C::Infinite>,
+    suffix: Option<C::Infinite>,
+) -> Option<&'a Arc<Node<C>>> {
+    if prefix.is_none() && suffix.is_none() {
+        return wc.con.as_ref();
+    }
+
+    let data = InfiniteValues::Affixed { prefix, suffix };
+    branches
+        .iter()
+        .find(|branch| branch.data == data)
+        .map(|branch| &branch.con)
+}
+
+// the prefix and suffix which a value not equal to any literal is grouped by
+pub(crate) fn affixes_of<C: Constructors>(
+    branches: &[InfiniteBranch<C>],
+    value: &C::Infinite,
+) -> (Option<C::Infinite>, Option<C::Infinite>) {
+    (
+        Side::Prefix.longest(&Side::Prefix.affixes(branches), value),
+        Side::Suffix.longest(&Side::Suffix.affixes(branches), value),
+    )
+}
+
+/// Which end of an infinite value an affix is matched against
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Side {
+    Prefix,
+    Suffix,
+}
+
+impl Side {
+    fn other(self) -> Self {
+        match self {
+            Side::Prefix => Side::Suffix,
+            Side::Suffix => Side::Prefix,
+        }
+    }
+
+    fn matches<I: InfiniteConstructor>(self, value: &I, affix: &I) -> bool {
+        match self {
+            Side::Prefix => value.starts_with(affix),
+            Side::Suffix => value.ends_with(affix),
+        }
+    }
+
+    // whether `value` is matched by a pattern with `affix` on this side
+    pub(crate) fn includes<I: InfiniteConstructor>(self, value: &I, affix: &Option<I>) -> bool {
+        affix
+            .as_ref()
+            .is_none_or(|affix| self.matches(value, affix))
+    }
+
+    // orders an affix of this side and one of the other side into a prefix and a suffix
+    fn order<I>(self, this: Option<I>, other: Option<I>) -> (Option<I>, Option<I>) {
+        match self {
+            Side::Prefix => (this, other),
+            Side::Suffix => (other, this),
+        }
+    }
+
+    // the affixes of this side which the values of an infinite tree are grouped by
+    pub(crate) fn affixes<C: Constructors>(
+        self,
+        branches: &[InfiniteBranch<C>],
+    ) -> Vec<C::Infinite> {
+        let mut affixes: Vec<C::Infinite> = vec![];
+        for branch in branches {
+            if let InfiniteValues::Affixed { prefix, suffix } = &branch.data {
+                let affix = match self {
+                    Side::Prefix => prefix,
+                    Side::Suffix => suffix,
+                };
+                if let Some(affix) = affix {
+                    if !affixes.contains(affix) {
+                        affixes.push(affix.clone());
+                    }
+                }
+            }
+        }
+        affixes
+    }
+
+    // the longest of the affixes which `value` starts or ends with. Since they're all part of
+    // the same value, each of them is contained in the longer ones.
+    pub(crate) fn longest<I: InfiniteConstructor + Clone>(
+        self,
+        affixes: &[I],
+        value: &I,
+    ) -> Option<I> {
+        affixes
+            .iter()
+            .filter(|affix| self.matches(value, affix))
+            .fold(None, |longest, affix| match longest {
+                Some(longest) if !self.matches(affix, &longest) => Some(longest),
+                _ => Some(affix.clone()),
+            })
+    }
+
+    // the groups of this side which are entirely covered by a pattern with `affix`
+    pub(crate) fn covered_by<I: InfiniteConstructor + Clone>(
+        self,
+        affixes: &[I],
+        affix: &Option<I>,
+    ) -> Vec<Option<I>> {
+        std::iter::once(None)
+            .chain(affixes.iter().cloned().map(Some))
+            .filter(|group| match group {
+                None => affix.is_none(),
+                Some(group) => self.includes(group, affix),
+            })
+            .collect()
+    }
+}
+
+/// Every Unicode scalar value
+pub(crate) const CHARS: RangeInclusive<char> = '\0'..=char::MAX;
+
 pub(crate) fn signed_min(bitsize: u32) -> i128 {
     -signed_max(bitsize) - 1
 }
 
 pub(crate) fn signed_max(bitsize: u32) -> i128 {
-    2i128.pow(bitsize - 1) - 1
+    i128::MAX >> (128 - bitsize)
 }
 
 pub(crate) fn unsigned_max(bitsize: u32) -> u128 {
-    2u128.pow(bitsize) - 1
+    u128::MAX >> (128 - bitsize)
 }
 
-impl<'t, C: Constructors> Merger<'t, C, RangeBranch<C, i128>> {
-    fn with_range(mut self, range: RangeInclusive<i128>) -> IsReachable {
+/// Numbers which can be used as the bounds of a range pattern
+pub(crate) trait RangeBound: Copy + Ord + std::fmt::Debug {
+    fn incr(self) -> Self;
+    fn decr(self) -> Self;
+
+    /// The parts of the range which can each be written as a single range pattern
+    fn contiguous(range: RangeInclusive<Self>) -> Vec<RangeInclusive<Self>> {
+        vec![range]
+    }
+}
+
+impl RangeBound for i128 {
+    fn incr(self) -> Self {
+        self + 1
+    }
+
+    fn decr(self) -> Self {
+        self - 1
+    }
+}
+
+impl RangeBound for usize {
+    fn incr(self) -> Self {
+        self + 1
+    }
+
+    fn decr(self) -> Self {
+        self - 1
+    }
+}
+
+// steps over the surrogates since they aren't valid chars
+impl RangeBound for char {
+    fn incr(self) -> Self {
+        match self {
+            '\u{D7FF}' => '\u{E000}',
+            c => char::from_u32(c as u32 + 1).unwrap(),
+        }
+    }
+
+    fn decr(self) -> Self {
+        match self {
+            '\u{E000}' => '\u{D7FF}',
+            c => char::from_u32(c as u32 - 1).unwrap(),
+        }
+    }
+}
+
+impl RangeBound for OrderedFloat {
+    fn incr(self) -> Self {
+        OrderedFloat::incr(self)
+    }
+
+    fn decr(self) -> Self {
+        OrderedFloat::decr(self)
+    }
+
+    // NaN can't be part of a range of other floats
+    fn contiguous(range: RangeInclusive<Self>) -> Vec<RangeInclusive<Self>> {
+        let (start, end) = (*range.start(), *range.end());
+        if start != OrderedFloat::NAN && end == OrderedFloat::NAN {
+            vec![start..=end.decr(), end..=end]
+        } else {
+            vec![range]
+        }
+    }
+}
+
+impl RangeBound for u128 {
+    fn incr(self) -> Self {
+        self + 1
+    }
+
+    fn decr(self) -> Self {
+        self - 1
+    }
+}
+
+// the parts of `full` which none of the ranges cover
+pub(crate) fn uncovered<N: RangeBound>(
+    mut ranges: Vec<RangeInclusive<N>>,
+    full: RangeInclusive<N>,
+) -> Vec<RangeInclusive<N>> {
+    ranges.sort_by_key(|range| *range.start());
+
+    let mut missing = vec![];
+    let mut next = *full.start();
+
+    for range in ranges {
+        if *range.start() > next {
+            missing.push(next..=range.start().decr());
+        }
+        if *range.end() >= *full.end() {
+            return missing;
+        }
+        next = next.max(range.end().incr());
+    }
+
+    missing.push(next..=*full.end());
+    missing
+}
+
+impl<'t, C: Constructors, N: RangeBound> Merger<'t, C, RangeBranch<C, N>> {
+    fn with_range(mut self, range: RangeInclusive<N>) -> MergeResult<C::Meta> {
         if self.ptr >= self.branches.len() {
             self.branches.push(Branch {
                 data: range,
-                con: self.src.drain_to_patterntree(),
+                con: Arc::new(self.src.drain_to_patterntree()),
             });
-            return IsReachable(true);
+            return Ok(IsReachable(true));
         }
 
-        let Branch { data: erange, con: econ } = &mut self.branches[self.ptr];
+        let Branch {
+            data: erange,
+            con: econ,
+        } = &mut self.branches[self.ptr];
         let mut e_start = *erange.start();
         let e_end = *erange.end();
         let start = *range.start();
@@ -281,7 +758,7 @@ impl<'t, C: Constructors> Merger<'t, C, RangeBranch<C, i128>> {
         }
 
         if range == *erange {
-            return self.src.merge_with(econ);
+            return self.src.merge_with(Arc::make_mut(econ));
         }
 
         let start_is_inside = start >= e_start;
@@ -291,7 +768,7 @@ impl<'t, C: Constructors> Merger<'t, C, RangeBranch<C, i128>> {
 
         if start_is_inside {
             if e_start != start {
-                let excluded_left_side = e_start..=start - 1;
+                let excluded_left_side = e_start..=start.decr();
                 *erange = start..=e_end;
                 e_start = start;
                 let econ = econ.clone();
@@ -301,14 +778,17 @@ impl<'t, C: Constructors> Merger<'t, C, RangeBranch<C, i128>> {
                 });
             }
         } else {
-            let extra_left_side = start..=e_start - 1;
-            is_reachable |= self.additional(extra_left_side);
+            let extra_left_side = start..=e_start.decr();
+            is_reachable |= self.additional(extra_left_side)?;
         }
 
         if end_is_inside {
             if e_end != end {
-                let excluded_right_side = end + 1..=e_end;
-                let Branch { data: erange, con: econ } = &mut self.branches[self.ptr];
+                let excluded_right_side = end.incr()..=e_end;
+                let Branch {
+                    data: erange,
+                    con: econ,
+                } = &mut self.branches[self.ptr];
                 let econ = econ.clone();
                 *erange = e_start..=end;
                 self.branches.push(Branch {
@@ -317,29 +797,38 @@ impl<'t, C: Constructors> Merger<'t, C, RangeBranch<C, i128>> {
                 });
             }
         } else {
-            let extra_right_side = e_end + 1..=end;
-            is_reachable |= self.additional(extra_right_side);
+            let extra_right_side = e_end.incr()..=end;
+            is_reachable |= self.additional(extra_right_side)?;
         }
 
         let Branch { con: econ, .. } = &mut self.branches[self.ptr];
-        is_reachable |= self.src.merge_with(econ);
+        is_reachable |= self.src.merge_with(Arc::make_mut(econ))?;
 
-        is_reachable
+        Ok(is_reachable)
     }
 
-    fn with_wildcard_signed_integer(self, _: C::Wildcard, bitsize: u32) -> IsReachable {
-        let full = signed_min(bitsize)..=signed_max(bitsize);
-        self.with_range(full)
-    }
-
-    fn additional(&mut self, range: RangeInclusive<i128>) -> IsReachable {
+    fn additional(&mut self, range: RangeInclusive<N>) -> MergeResult<C::Meta> {
         let mut this = self.src.clone().into_merger(self.branches);
         this.ptr += 1;
         this.with_range(range)
     }
 
-    fn try_next(mut self, range: RangeInclusive<i128>) -> IsReachable {
+    fn try_next(mut self, range: RangeInclusive<N>) -> MergeResult<C::Meta> {
         self.ptr += 1;
         self.with_range(range)
     }
 }
+
+impl<'t, C: Constructors> Merger<'t, C, RangeBranch<C, i128>> {
+    fn with_wildcard_signed_integer(self, _: C::Wildcard, bitsize: u32) -> MergeResult<C::Meta> {
+        let full = signed_min(bitsize)..=signed_max(bitsize);
+        self.with_range(full)
+    }
+}
+
+impl<'t, C: Constructors> Merger<'t, C, RangeBranch<C, u128>> {
+    fn with_wildcard_unsigned_integer(self, _: C::Wildcard, bitsize: u32) -> MergeResult<C::Meta> {
+        let full = 0..=unsigned_max(bitsize);
+        self.with_range(full)
+    }
+}
@@ -0,0 +1,393 @@
+use super::merge::{overlaps, signed_max, signed_min, unsigned_max, RangeBound, CHARS};
+use super::{OrderedFloat, PatternTree, Tags};
+use crate::{Constructor, Constructors, Pattern, SumtypeConstructor};
+use itertools::Itertools;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// The patterns missing from a match, merged into as few witnesses as possible
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "C: crate::SerializeConstructors",
+        deserialize = "C: crate::DeserializeConstructors<'de>"
+    ))
+)]
+pub struct Missing<C: Constructors> {
+    pub patterns: Vec<Pattern<C>>,
+    /// The amount of witnesses left out after the limit was reached
+    pub more: usize,
+}
+
+impl<C: Constructors> PatternTree<C> {
+    /// Like `generate_missing_patterns` but with the witnesses merged into as few as possible
+    ///
+    /// Adjacent ranges are merged, and sibling variants which together make up their whole sum
+    /// type are collapsed into a wildcard. Only the first `limit` witnesses are kept, with the
+    /// rest being counted in `Missing::more`. The limit is applied after merging, since the
+    /// witnesses past it could still be merged into the ones before it.
+    pub fn minimal_missing_patterns(&self, limit: Option<usize>) -> Missing<C> {
+        let mut patterns = minimize(self.generate_missing_patterns());
+        let more = match limit {
+            Some(limit) if limit < patterns.len() => patterns.split_off(limit).len(),
+            _ => 0,
+        };
+        Missing { patterns, more }
+    }
+}
+
+impl<S: fmt::Display, C: Constructors<SumType = S>> fmt::Display for Missing<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.patterns.iter().format(", "))?;
+        if self.more != 0 {
+            write!(f, " and {} more", self.more)?;
+        }
+        Ok(())
+    }
+}
+
+// merges the witnesses with each other until none of them can be merged any further
+//
+// Every round merges the witnesses which only differ in the range or variant at one position, for
+// each of the positions in turn. A round which merges nothing is the last one, so there are never
+// more rounds than witnesses.
+fn minimize<C: Constructors>(mut patterns: Vec<Pattern<C>>) -> Vec<Pattern<C>> {
+    loop {
+        let before = patterns.len();
+        for path in leaf_paths(&patterns) {
+            patterns = merge_at(patterns, &path);
+        }
+        if patterns.len() == before {
+            return patterns;
+        }
+    }
+}
+
+// merges the witnesses which are the same apart from the range or variant at `path`, keeping the
+// merged ones where the first of them was
+fn merge_at<C: Constructors>(patterns: Vec<Pattern<C>>, path: &[usize]) -> Vec<Pattern<C>> {
+    let mut merged = vec![];
+    // the witnesses with a wildcard in place of their leaf, and the index of the first of them
+    let mut groups: Vec<(usize, Pattern<C>, Vec<Pattern<C>>)> = vec![];
+
+    for (i, p) in patterns.into_iter().enumerate() {
+        if !p.at(path).is_some_and(is_leaf) {
+            merged.push((i, p));
+            continue;
+        }
+
+        let rest = p.replaced(path, Pattern::wildcard(C::Wildcard::default()));
+        match groups.iter_mut().find(|(_, r, _)| equal(r, &rest)) {
+            Some((_, _, members)) => members.push(p),
+            None => groups.push((i, rest, vec![p])),
+        }
+    }
+
+    for (i, rest, members) in groups {
+        let group = merge_group(rest, members, path);
+        merged.extend(group.into_iter().map(|p| (i, p)));
+    }
+    merged.sort_by_key(|(i, _)| *i);
+    merged.into_iter().map(|(_, p)| p).collect()
+}
+
+// the variants are collapsed into a wildcard if they make up their whole sum type, while the
+// ranges are joined in a single pass over them in order
+fn merge_group<C: Constructors>(
+    rest: Pattern<C>,
+    mut members: Vec<Pattern<C>>,
+    path: &[usize],
+) -> Vec<Pattern<C>> {
+    if let Some((type_, _)) = members[0].at(path).and_then(bare_variant) {
+        let tags = members
+            .iter()
+            .filter_map(|p| Some(bare_variant(p.at(path)?)?.1))
+            .collect::<Vec<_>>();
+        let whole = type_.tags().iter().all(|tag| tags.contains(tag));
+        return match whole && !type_.is_non_exhaustive() {
+            true => vec![rest],
+            false => members,
+        };
+    }
+
+    members.sort_by(|a, b| start_order(leaf(a, path), leaf(b, path)));
+    let mut merged: Vec<Pattern<C>> = vec![];
+    for p in members {
+        let joined = merged
+            .last()
+            .and_then(|last| join(leaf(last, path), leaf(&p, path)));
+        match joined {
+            Some(constr) => {
+                let last = merged.last_mut().unwrap();
+                *last = last.replaced(path, Pattern::new(constr));
+            }
+            None => merged.push(p),
+        }
+    }
+    merged
+}
+
+fn leaf<'p, C: Constructors>(p: &'p Pattern<C>, path: &[usize]) -> &'p Constructor<C> {
+    &p.at(path).expect("leaf is not in the pattern").constr
+}
+
+// the order of range constructors by where they start
+fn start_order<C: Constructors>(a: &Constructor<C>, b: &Constructor<C>) -> Ordering {
+    match (a, b) {
+        (
+            Constructor::SignedInteger { range: a, .. },
+            Constructor::SignedInteger { range: b, .. },
+        ) => a.start().cmp(b.start()),
+        (
+            Constructor::UnsignedInteger { range: a, .. },
+            Constructor::UnsignedInteger { range: b, .. },
+        ) => a.start().cmp(b.start()),
+        (Constructor::Char { range: a }, Constructor::Char { range: b }) => {
+            a.start().cmp(b.start())
+        }
+        (Constructor::Float { range: a }, Constructor::Float { range: b }) => {
+            OrderedFloat::from(*a.start()).cmp(&OrderedFloat::from(*b.start()))
+        }
+        _ => Ordering::Equal,
+    }
+}
+
+// a range or a variant whose parameters are all wildcards, which witnesses can be merged by
+fn is_leaf<C: Constructors>(p: &Pattern<C>) -> bool {
+    bare_variant(p).is_some()
+        || matches!(
+            p.constr,
+            Constructor::SignedInteger { .. }
+                | Constructor::UnsignedInteger { .. }
+                | Constructor::Char { .. }
+                | Constructor::Float { .. }
+        )
+}
+
+// the paths to every leaf in the patterns
+fn leaf_paths<C: Constructors>(patterns: &[Pattern<C>]) -> Vec<Vec<usize>> {
+    fn collect<C: Constructors>(p: &Pattern<C>, path: Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        if is_leaf(p) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+            return;
+        }
+        for (i, param) in p.params.iter().enumerate() {
+            let mut path = path.clone();
+            path.push(i);
+            collect(param, path, paths);
+        }
+    }
+
+    let mut paths = vec![];
+    for p in patterns {
+        collect(p, vec![], &mut paths);
+    }
+    paths
+}
+
+// whether every value matched by `b` is matched by `a`
+fn covers<C: Constructors>(a: &Pattern<C>, b: &Pattern<C>) -> bool {
+    match (&a.constr, &b.constr) {
+        (Constructor::Wildcard(_), _) => true,
+        (
+            Constructor::SignedInteger { range: a, bitsize },
+            Constructor::SignedInteger {
+                range: b,
+                bitsize: bs,
+            },
+        ) => bitsize == bs && contains(a, b),
+        (
+            Constructor::UnsignedInteger { range: a, bitsize },
+            Constructor::UnsignedInteger {
+                range: b,
+                bitsize: bs,
+            },
+        ) => bitsize == bs && contains(a, b),
+        (Constructor::Char { range: a }, Constructor::Char { range: b }) => contains(a, b),
+        (Constructor::Float { range: a }, Constructor::Float { range: b }) => contains(
+            &OrderedFloat::range(a.clone()),
+            &OrderedFloat::range(b.clone()),
+        ),
+        (a_constr, b_constr) => {
+            same_constr(a_constr, b_constr)
+                && a.params.len() == b.params.len()
+                && a.params.iter().zip(&b.params).all(|(a, b)| covers(a, b))
+        }
+    }
+}
+
+fn equal<C: Constructors>(a: &Pattern<C>, b: &Pattern<C>) -> bool {
+    covers(a, b) && covers(b, a)
+}
+
+// whether the constructors are the same, apart from the names of wildcards
+fn same_constr<C: Constructors>(a: &Constructor<C>, b: &Constructor<C>) -> bool {
+    match (a, b) {
+        (
+            Constructor::SignedInteger { range, bitsize },
+            Constructor::SignedInteger {
+                range: r,
+                bitsize: bs,
+            },
+        ) => range == r && bitsize == bs,
+        (
+            Constructor::UnsignedInteger { range, bitsize },
+            Constructor::UnsignedInteger {
+                range: r,
+                bitsize: bs,
+            },
+        ) => range == r && bitsize == bs,
+        (Constructor::Char { range }, Constructor::Char { range: r }) => range == r,
+        (Constructor::Float { range }, Constructor::Float { range: r }) => {
+            OrderedFloat::range(range.clone()) == OrderedFloat::range(r.clone())
+        }
+        (Constructor::Variant { type_, tag }, Constructor::Variant { type_: t, tag: tg }) => {
+            type_ == t && tag == tg
+        }
+        (Constructor::Infinite(a), Constructor::Infinite(b)) => a == b,
+        (
+            Constructor::InfiniteAffix { prefix, suffix },
+            Constructor::InfiniteAffix {
+                prefix: p,
+                suffix: s,
+            },
+        ) => prefix == p && suffix == s,
+        (Constructor::Lenghted(a), Constructor::Lenghted(b)) => a == b,
+        (
+            Constructor::LengthedRest { constr, rest_at },
+            Constructor::LengthedRest {
+                constr: c,
+                rest_at: r,
+            },
+        ) => constr == c && rest_at == r,
+        (
+            Constructor::LengthRange {
+                constr,
+                lengths,
+                rest_at,
+            },
+            Constructor::LengthRange {
+                constr: c,
+                lengths: l,
+                rest_at: r,
+            },
+        ) => constr == c && lengths == l && rest_at == r,
+        (Constructor::Constant(a), Constructor::Constant(b)) => a == b,
+        (Constructor::Opaque(a), Constructor::Opaque(b)) => a == b,
+        (Constructor::Wildcard(_), Constructor::Wildcard(_)) => true,
+        _ => false,
+    }
+}
+
+// joins two ranges which together form a single range, which is a wildcard if it's every value
+fn join<C: Constructors>(a: &Constructor<C>, b: &Constructor<C>) -> Option<Constructor<C>> {
+    match (a, b) {
+        (
+            &Constructor::SignedInteger {
+                range: ref a,
+                bitsize,
+            },
+            Constructor::SignedInteger {
+                range: b,
+                bitsize: bs,
+            },
+        ) if bitsize == *bs => {
+            let full = signed_min(bitsize as u32)..=signed_max(bitsize as u32);
+            let range = join_ranges(a, b)?;
+            Some(whole_or(range, full, |range| Constructor::SignedInteger {
+                range,
+                bitsize,
+            }))
+        }
+        (
+            &Constructor::UnsignedInteger {
+                range: ref a,
+                bitsize,
+            },
+            Constructor::UnsignedInteger {
+                range: b,
+                bitsize: bs,
+            },
+        ) if bitsize == *bs => {
+            let full = 0..=unsigned_max(bitsize as u32);
+            let range = join_ranges(a, b)?;
+            Some(whole_or(range, full, |range| {
+                Constructor::UnsignedInteger { range, bitsize }
+            }))
+        }
+        (Constructor::Char { range: a }, Constructor::Char { range: b }) => {
+            let range = join_ranges(a, b)?;
+            Some(whole_or(range, CHARS, |range| Constructor::Char { range }))
+        }
+        // NaN can't be part of a range with the other floats, so floats are never every value
+        (Constructor::Float { range: a }, Constructor::Float { range: b }) => {
+            let (a, b) = (
+                OrderedFloat::range(a.clone()),
+                OrderedFloat::range(b.clone()),
+            );
+            let range = join_ranges(&a, &b)?;
+            let [range] = <[_; 1]>::try_from(OrderedFloat::contiguous(range)).ok()?;
+            Some(Constructor::Float {
+                range: range.start().to_f64()..=range.end().to_f64(),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn join_ranges<N: RangeBound>(
+    a: &RangeInclusive<N>,
+    b: &RangeInclusive<N>,
+) -> Option<RangeInclusive<N>> {
+    let (first, second) = if a.start() <= b.start() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let adjacent = first.end() < second.start() && first.end().incr() == *second.start();
+    (overlaps(a, b) || adjacent).then(|| *first.start()..=*first.end().max(second.end()))
+}
+
+fn whole_or<N: PartialEq, C: Constructors>(
+    range: RangeInclusive<N>,
+    full: RangeInclusive<N>,
+    to_constr: impl FnOnce(RangeInclusive<N>) -> Constructor<C>,
+) -> Constructor<C> {
+    if range == full {
+        Constructor::Wildcard(C::Wildcard::default())
+    } else {
+        to_constr(range)
+    }
+}
+
+fn contains<N: Ord>(a: &RangeInclusive<N>, b: &RangeInclusive<N>) -> bool {
+    a.start() <= b.start() && b.end() <= a.end()
+}
+
+// a variant whose parameters are all wildcards
+fn bare_variant<C: Constructors>(p: &Pattern<C>) -> Option<(&C::SumType, u64)> {
+    match &p.constr {
+        Constructor::Variant { type_, tag }
+            if p.params
+                .iter()
+                .all(|p| matches!(p.constr, Constructor::Wildcard(_))) =>
+        {
+            Some((type_, *tag))
+        }
+        _ => None,
+    }
+}
+
+impl<C: Constructors> Pattern<C> {
+    fn replaced(&self, path: &[usize], with: Self) -> Self {
+        let mut p = self.clone();
+        let param = path.iter().fold(&mut p, |param, &i| &mut param.params[i]);
+        *param = with;
+        p
+    }
+}
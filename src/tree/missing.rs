@@ -1,5 +1,10 @@
-use super::{Branch, PatternTree, RangeBranch, WildcardKeeper};
-use crate::{ConstantConstructor, Constructor, Constructors, Pattern, SumtypeConstructor};
+use super::merge::{signed_max, signed_min, uncovered, unsigned_max, RangeBound, CHARS};
+use super::{
+    max_length, Branch, InfiniteValues, Length, LengthedBranch, Node, OrderedFloat, PatternTree,
+};
+use super::{RangeBranch, Tags, WildcardKeeper};
+use crate::{ConstantConstructor, Constructor, Constructors, Pattern};
+use std::ops::RangeInclusive;
 
 #[derive(Debug)]
 struct Progress<'a, C: Constructors> {
@@ -12,88 +17,101 @@ struct ParamProgress<C: Constructors> {
     buf: Vec<Pattern<C>>,
     constr: Constructor<C>,
     remaining: usize,
+    // wildcards inserted into the parameters once they're constructed, as (position, amount)
+    padding: (usize, usize),
     parent: Box<Option<Self>>,
 }
 
 impl<C: Constructors> PatternTree<C> {
     pub fn generate_missing_patterns(&self) -> Vec<Pattern<C>> {
         let mut versions = Versions(vec![]);
-        self.get_missing(Progress::new(&mut versions));
+        self.root.get_missing(Progress::new(&mut versions));
         versions.0
     }
+}
 
+impl<C: Constructors> Node<C> {
     fn get_missing(&self, mut prog: Progress<'_, C>) {
         match self {
-            &PatternTree::SignedInteger { bitsize, ref branches } => {
-                let has_missing_ranges = |branches: &[RangeBranch<C, i128>]| -> bool {
-                    // lol wtf, we need to fix this.
-                    let together = ((branches
-                        .iter()
-                        .map(|Branch { data: range, .. }| i128::abs(*range.start() - *range.end()))
-                        .sum::<i128>()
-                        + (branches.len() as i128))
-                        / 2)
-                        - 1;
-                    let max = super::merge::signed_max(bitsize as u32);
-                    together != max as i128
-                };
-
-                if has_missing_ranges(branches) {
-                    return prog.clone().rest_is_missing();
-                }
-
-                // sub-missing of included ranges
-                for Branch { data: range, con } in branches {
-                    let pattern =
-                        Pattern::new(Constructor::SignedInteger { range: range.clone(), bitsize });
-                    let prog = prog.clone().include(pattern);
-                    con.get_missing(prog)
-                }
+            &Node::SignedInteger {
+                bitsize,
+                ref branches,
+            } => {
+                let full = signed_min(bitsize as u32)..=signed_max(bitsize as u32);
+                let to_constr = |range| Constructor::SignedInteger { range, bitsize };
+                prog.include_ranges(branches, full, to_constr);
+            }
+            &Node::UnsignedInteger {
+                bitsize,
+                ref branches,
+            } => {
+                let full = 0..=unsigned_max(bitsize as u32);
+                let to_constr = |range| Constructor::UnsignedInteger { range, bitsize };
+                prog.include_ranges(branches, full, to_constr);
+            }
+            Node::Char(branches) => {
+                prog.include_ranges(branches, CHARS, |range| Constructor::Char { range })
             }
-            PatternTree::Variant(type_, branches) => {
-                let max = type_.max();
-                for tag in 0..=max {
+            Node::Float(branches) => {
+                prog.include_ranges(branches, OrderedFloat::all(), |range| Constructor::Float {
+                    range: range.start().to_f64()..=range.end().to_f64(),
+                })
+            }
+            Node::Variant(type_, branches) => {
+                for tag in type_.tags() {
+                    // the unknown variants of a non-exhaustive sum type can only be named by `_`
+                    let constr = Constructor::Variant {
+                        type_: type_.clone(),
+                        tag,
+                    };
+                    let pattern = match type_.is_unknown(tag) {
+                        true => Pattern::wildcard(C::Wildcard::default()),
+                        false => Pattern::new(constr.clone()),
+                    };
+
                     match branches.iter().find(|branch| branch.data == tag) {
+                        Some(branch) if type_.is_unknown(tag) => {
+                            branch.con.get_missing(prog.clone().include(pattern))
+                        }
                         Some(branch) => {
-                            let params = type_.params_for(branch.data);
-                            branch.con.get_missing(prog.clone().new_params(
-                                Constructor::Variant { type_: type_.clone(), tag },
-                                params,
-                            ));
+                            let params = type_.params_of(tag);
+                            branch
+                                .con
+                                .get_missing(prog.clone().new_params(constr, params));
+                        }
+                        None => {
+                            let params = (0..type_.params_of(tag))
+                                .map(|_| Pattern::wildcard(C::Wildcard::default()))
+                                .collect();
+                            prog.clone().missing(pattern.with_params(params))
                         }
-                        None => prog.clone().rest_is_missing(),
                     }
                 }
             }
-            PatternTree::Lengthed(constr, wc, branches) => {
-                if !prog.clone().include_wildcard(wc) {
-                    prog.include_branches(
-                        branches,
-                        |_| Constructor::Lenghted(constr.clone()),
-                        |params| *params,
-                    );
-                }
-            }
-            PatternTree::Constant(constr, con) => {
+            Node::Lengthed(constr, branches) => prog.include_lengths(constr, branches),
+            Node::Constant(constr, con) => {
                 let params = constr.len_requirement();
                 con.get_missing(prog.new_params(Constructor::Constant(constr.clone()), params))
             }
-            PatternTree::Infinite(wc, branches) => {
+            Node::Infinite(wc, branches) => {
+                // every group of values starts out with what the wildcards covered, so there's
+                // nothing more missing from them unless the wildcards left something out
                 if !prog.clone().include_wildcard(wc) {
-                    prog.include_branches(
-                        branches,
-                        |constr| Constructor::Infinite(constr.clone()),
-                        |_| 0,
-                    );
+                    let to_constr = |values: &InfiniteValues<C::Infinite>| match values.clone() {
+                        InfiniteValues::Literal(value) => Constructor::Infinite(value),
+                        InfiniteValues::Affixed { prefix, suffix } => {
+                            Constructor::InfiniteAffix { prefix, suffix }
+                        }
+                    };
+                    prog.include_branches(branches, to_constr, |_| 0);
                 }
             }
-            PatternTree::None => {
-                assert!(matches!(prog.params, None));
+            Node::End(_) => {
+                assert!(prog.params.is_none());
             }
-            PatternTree::UnknownWildcard(wc) => {
+            Node::UnknownWildcard(wc) => {
                 prog.include_wildcard(wc);
             }
-            other => todo!("{:?}", other),
         }
     }
 }
@@ -103,13 +121,9 @@ struct Versions<C: Constructors>(Vec<Pattern<C>>);
 
 impl<'a, C: Constructors> Progress<'a, C> {
     fn new(final_: &'a mut Versions<C>) -> Self {
-        Self { final_, params: None }
-    }
-
-    fn into_parent(self, constr: Constructor<C>, params: usize) -> Progress<'a, C> {
-        Progress {
-            final_: self.final_,
-            params: Some(ParamProgress::new(constr, params, self.params.clone())),
+        Self {
+            final_,
+            params: None,
         }
     }
 
@@ -127,12 +141,12 @@ impl<'a, C: Constructors> Progress<'a, C> {
                 pprog.remaining -= 1;
                 pprog.buf.push(pattern);
                 if pprog.remaining == 0 {
-                    let constructed = Pattern::new(pprog.constr).with_params(pprog.buf);
+                    let parent = pprog.parent.clone();
                     Progress {
                         final_: self.final_,
-                        params: *pprog.parent,
+                        params: *parent,
                     }
-                    .include(constructed)
+                    .include(pprog.construct())
                 } else {
                     Progress {
                         final_: self.final_,
@@ -154,15 +168,54 @@ impl<'a, C: Constructors> Progress<'a, C> {
         }
     }
 
+    fn include_ranges<N: RangeBound>(
+        &mut self,
+        branches: &[RangeBranch<C, N>],
+        full: RangeInclusive<N>,
+        to_constr: impl Fn(RangeInclusive<N>) -> Constructor<C>,
+    ) {
+        let ranges = branches.iter().map(|branch| branch.data.clone()).collect();
+        for range in uncovered(ranges, full).into_iter().flat_map(N::contiguous) {
+            self.clone().missing(Pattern::new(to_constr(range)));
+        }
+
+        // sub-missing of included ranges
+        for Branch { data: range, con } in branches {
+            let pattern = Pattern::new(to_constr(range.clone()));
+            con.get_missing(self.clone().include(pattern));
+        }
+    }
+
+    fn include_lengths(&mut self, constr: &C::Lengthed, branches: &[LengthedBranch<C>]) {
+        let ranges = branches
+            .iter()
+            .map(|branch| branch.data.lengths.clone())
+            .collect();
+        for lengths in uncovered(ranges, 0..=max_length(constr)) {
+            let (constr, length) = length_constructor(constr, &Length::range(lengths));
+            let params = vec![Pattern::wildcard(C::Wildcard::default()); length];
+            self.clone()
+                .missing(Pattern::new(constr).with_params(params));
+        }
+
+        for Branch { data, con } in branches {
+            let (constr, length) = length_constructor(constr, data);
+            let padding = (data.prefix, length - data.params());
+            con.get_missing(
+                self.clone()
+                    .new_padded_params(constr, data.params(), padding),
+            );
+        }
+    }
+
     fn include_wildcard(self, wc: &WildcardKeeper<C>) -> bool {
-        let pattern = Pattern::wildcard(C::Wildcard::default());
         match wc.con.as_deref() {
             None => {
-                self.include(pattern).rest_is_missing();
+                self.rest_is_missing();
                 false
             }
             Some(con) => {
-                let prog = self.include(pattern);
+                let prog = self.include(Pattern::wildcard(C::Wildcard::default()));
                 con.get_missing(prog);
                 true
             }
@@ -170,31 +223,55 @@ impl<'a, C: Constructors> Progress<'a, C> {
     }
 
     fn rest_is_missing(self) {
+        self.missing(Pattern::wildcard(C::Wildcard::default()))
+    }
+
+    // the pattern at the current position is missing regardless of what follows it
+    fn missing(self, pattern: Pattern<C>) {
         let wc = Pattern::wildcard(C::Wildcard::default());
 
         match self.params {
             Some(mut pprog) => {
-                for _ in 0..pprog.remaining {
+                pprog.buf.push(pattern);
+                for _ in 1..pprog.remaining {
                     pprog.buf.push(wc.clone());
                 }
-                let constructed = Pattern::new(pprog.constr).with_params(pprog.buf);
+                let parent = pprog.parent.clone();
+                let constructed = pprog.construct();
 
-                match *pprog.parent {
+                match *parent {
                     None => self.final_.0.push(constructed),
-                    Some(parent) => todo!("recurse"),
+                    Some(parent) => Progress {
+                        final_: self.final_,
+                        params: Some(parent),
+                    }
+                    .missing(constructed),
                 }
             }
             None => {
-                self.final_.0.push(wc);
+                self.final_.0.push(pattern);
             }
         }
     }
 
     fn new_params(self, constr: Constructor<C>, params: usize) -> Self {
+        self.new_padded_params(constr, params, (0, 0))
+    }
+
+    fn new_padded_params(
+        self,
+        constr: Constructor<C>,
+        params: usize,
+        padding: (usize, usize),
+    ) -> Self {
+        let pprog = ParamProgress::new(constr, params, self.params.clone()).padded(padding);
         if params == 0 {
-            self.include(Pattern::new(constr))
+            self.include(pprog.construct())
         } else {
-            self.into_parent(constr, params)
+            Progress {
+                final_: self.final_,
+                params: Some(pprog),
+            }
         }
     }
 }
@@ -205,7 +282,47 @@ impl<C: Constructors> ParamProgress<C> {
             remaining: params,
             buf: Vec::with_capacity(params),
             constr,
+            padding: (0, 0),
             parent: Box::new(parent),
         }
     }
+
+    fn padded(mut self, padding: (usize, usize)) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    fn construct(mut self) -> Pattern<C> {
+        let (at, n) = self.padding;
+        let wildcards = std::iter::repeat_n(Pattern::wildcard(C::Wildcard::default()), n);
+        self.buf.splice(at..at, wildcards);
+        Pattern::new(self.constr).with_params(self.buf)
+    }
+}
+
+// the constructor for the lengths, together with its amount of parameters. Since the lengths may
+// be unbounded, the ones up to the maximum are expressed as a rest pattern, and other ranges as a
+// length range keeping the parameters around the rest.
+fn length_constructor<C: Constructors>(
+    constr: &C::Lengthed,
+    length: &Length,
+) -> (Constructor<C>, usize) {
+    let (start, end) = (*length.lengths.start(), *length.lengths.end());
+    if start == end {
+        (Constructor::Lenghted(constr.clone()), start)
+    } else if end == max_length(constr) {
+        let rest_at = length.prefix + (start - length.params());
+        let constr = Constructor::LengthedRest {
+            constr: constr.clone(),
+            rest_at,
+        };
+        (constr, start)
+    } else {
+        let constr = Constructor::LengthRange {
+            constr: constr.clone(),
+            lengths: start..=end,
+            rest_at: length.prefix,
+        };
+        (constr, length.params())
+    }
 }
@@ -1,18 +1,56 @@
 use super::{
-    ConstantConstructor, Constructor, Constructors, FlatPatterns, Pattern, SumtypeConstructor,
+    ConstantConstructor, Constructor, Constructors, FlatPatterns, LengthedConstructor, Pattern,
+    SumtypeConstructor,
 };
 use itertools::Itertools;
 use std::fmt;
 use std::ops::RangeInclusive;
+use std::sync::Arc;
 
 pub(crate) type Params = usize;
 
+mod arms;
+mod decision;
+mod dot;
+pub use decision::{Access, Affixes, Decision, Param};
 pub(crate) mod merge;
-use merge::Merge;
+use merge::{signed_max, signed_min, unsigned_max, Merge};
+mod minimize;
+pub use minimize::Missing;
 mod missing;
+mod remove;
 
+/// The patterns of the arms of a match, merged into a trie over their constructors
 #[derive(Clone, Debug)]
-pub enum PatternTree<C: Constructors> {
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "C: crate::SerializeConstructors",
+        deserialize = "C: crate::DeserializeConstructors<'de>"
+    ))
+)]
+pub struct PatternTree<C: Constructors> {
+    root: Node<C>,
+    // the arm the next pattern is included as, which every included pattern uses up whether it's
+    // reachable or not
+    next_arm: Arm,
+}
+
+/// A node of a `PatternTree`, branching on the constructors found at one position of the patterns
+///
+/// The continuations are reference counted, so copies of a tree share the nodes neither of them
+/// has changed since.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "C: crate::SerializeConstructors",
+        deserialize = "C: crate::DeserializeConstructors<'de>"
+    ))
+)]
+pub enum Node<C: Constructors> {
     SignedInteger {
         bitsize: u8,
         branches: Vec<RangeBranch<C, i128>>,
@@ -21,170 +59,448 @@ pub enum PatternTree<C: Constructors> {
         bitsize: u8,
         branches: Vec<RangeBranch<C, u128>>,
     },
+    Char(Vec<RangeBranch<C, char>>),
+    Float(Vec<RangeBranch<C, OrderedFloat>>),
 
     Variant(C::SumType, Vec<VariantBranch<C>>),
-    Lengthed(C::Lengthed, WildcardKeeper<C>, Vec<LengthedBranch<C>>),
-    Constant(C::Constant, Box<Self>),
+    Lengthed(C::Lengthed, Vec<LengthedBranch<C>>),
+    Constant(C::Constant, Arc<Self>),
     Infinite(WildcardKeeper<C>, Vec<InfiniteBranch<C>>),
 
     UnknownWildcard(WildcardKeeper<C>),
-    None,
+    /// The end of a pattern, holding the arms which cover it in the order they were included
+    ///
+    /// The first of them is the arm which values ending up here are matched by, while the others
+    /// take over once it's removed.
+    End(Vec<Arm>),
 }
 
+/// The index of the pattern (match arm) which a `PatternTree` was built from
+pub type Arm = usize;
+
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "C: crate::SerializeConstructors, A: serde::Serialize",
+        deserialize = "C: crate::DeserializeConstructors<'de>, A: serde::Deserialize<'de>"
+    ))
+)]
 pub struct Branch<C: Constructors, A> {
-    pub(crate) con: PatternTree<C>,
+    pub(crate) con: Arc<Node<C>>,
     pub(crate) data: A,
 }
 
 trait Branches<C: Constructors, A> {
-    fn get_matching(&mut self, a: &A) -> Option<&mut PatternTree<C>>;
+    fn get_matching(&mut self, a: &A) -> Option<&mut Node<C>>;
     fn on_continuation(
         &mut self,
         params: usize,
         params_of: impl Fn(&A) -> usize,
-        f: &mut impl FnMut(&mut PatternTree<C>),
+        f: &mut impl FnMut(&mut Node<C>),
     );
 }
 
 impl<C: Constructors, A: PartialEq> Branches<C, A> for Vec<Branch<C, A>> {
-    fn get_matching(&mut self, a: &A) -> Option<&mut PatternTree<C>> {
+    fn get_matching(&mut self, a: &A) -> Option<&mut Node<C>> {
         self.iter_mut()
-            .find_map(|Branch { data, con }| if data == a { Some(con) } else { None })
+            .find_map(|Branch { data, con }| (data == a).then(|| Arc::make_mut(con)))
     }
 
     fn on_continuation(
         &mut self,
         params: usize,
         params_of: impl Fn(&A) -> usize,
-        f: &mut impl FnMut(&mut PatternTree<C>),
+        f: &mut impl FnMut(&mut Node<C>),
     ) {
-        self.iter_mut()
-            .for_each(|Branch { data, con }| con.on_continuation(params + params_of(data), f))
+        self.iter_mut().for_each(|Branch { data, con }| {
+            Arc::make_mut(con).on_continuation(params + params_of(data), f)
+        })
     }
 }
 
 pub(crate) type VariantBranch<C> = Branch<C, u64>;
-pub(crate) type LengthedBranch<C> = Branch<C, Params>;
-pub(crate) type InfiniteBranch<C> = Branch<C, <C as Constructors>::Infinite>;
+pub(crate) type LengthedBranch<C> = Branch<C, Length>;
+pub(crate) type InfiniteBranch<C> = Branch<C, InfiniteValues<<C as Constructors>::Infinite>>;
 pub(crate) type RangeBranch<C, N> = Branch<C, RangeInclusive<N>>;
 
+/// A float mapped onto an integer with the same ordering, so that float ranges can be split like
+/// integer ranges.
+///
+/// `-0.0` is mapped onto `0.0` and every NaN onto a single value above positive infinity.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OrderedFloat(u64);
+
+impl OrderedFloat {
+    const SIGN: u64 = 1 << 63;
+    const NEGATIVE_ZERO: OrderedFloat = OrderedFloat(!Self::SIGN);
+    pub(crate) const NAN: OrderedFloat = OrderedFloat(Self::INFINITY.0 + 1);
+    const INFINITY: OrderedFloat = OrderedFloat(0x7FF0_0000_0000_0000 | Self::SIGN);
+
+    /// Every float from negative infinity up to and including NaN
+    pub(crate) fn all() -> RangeInclusive<Self> {
+        Self::from(f64::NEG_INFINITY)..=Self::NAN
+    }
+
+    pub(crate) fn range(range: RangeInclusive<f64>) -> RangeInclusive<Self> {
+        let (start, end) = (*range.start(), *range.end());
+        assert!(
+            start.is_nan() == end.is_nan(),
+            "float range with only one of its bounds being NaN"
+        );
+        assert!(
+            start <= end || start.is_nan(),
+            "float range with its bounds swapped"
+        );
+        Self::from(start)..=Self::from(end)
+    }
+
+    pub(crate) fn to_f64(self) -> f64 {
+        match self {
+            Self::NAN => f64::NAN,
+            Self(bits) if bits & Self::SIGN != 0 => f64::from_bits(bits & !Self::SIGN),
+            Self(bits) => f64::from_bits(!bits),
+        }
+    }
+
+    pub(crate) fn incr(self) -> Self {
+        match self {
+            Self(bits) if bits + 1 == Self::NEGATIVE_ZERO.0 => Self(bits + 2),
+            Self(bits) => Self(bits + 1),
+        }
+    }
+
+    pub(crate) fn decr(self) -> Self {
+        match self {
+            Self(bits) if bits - 1 == Self::NEGATIVE_ZERO.0 => Self(bits - 2),
+            Self(bits) => Self(bits - 1),
+        }
+    }
+}
+
+impl From<f64> for OrderedFloat {
+    fn from(f: f64) -> Self {
+        if f.is_nan() {
+            Self::NAN
+        } else if f == 0.0 {
+            Self(Self::SIGN)
+        } else if f.is_sign_negative() {
+            Self(!f.to_bits())
+        } else {
+            Self(f.to_bits() | Self::SIGN)
+        }
+    }
+}
+
+impl fmt::Debug for OrderedFloat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.to_f64())
+    }
+}
+
+/// The values covered by a branch of an infinite value
+///
+/// Values equal to a literal have a branch of their own. The others are grouped by the longest of
+/// the prefixes and suffixes from the tree's patterns which they start and end with, where the
+/// values without either are covered by the wildcard of the tree instead.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InfiniteValues<I> {
+    Literal(I),
+    Affixed {
+        prefix: Option<I>,
+        suffix: Option<I>,
+    },
+}
+
+/// The lengths covered by a branch of a lengthed value
+///
+/// The continuation of the branch starts with the `prefix` parameters counted from the start of
+/// the value followed by the `suffix` parameters counted from its end. Any parameters in between
+/// are wildcards.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Length {
+    pub(crate) lengths: RangeInclusive<usize>,
+    pub(crate) prefix: usize,
+    pub(crate) suffix: usize,
+}
+
+/// The largest amount of parameters of a lengthed value, which is `usize::MAX` if it's unbounded
+pub(crate) fn max_length(constr: &impl LengthedConstructor) -> usize {
+    constr.max_length().unwrap_or(usize::MAX)
+}
+
+// the position of a parameter of a lengthed value
+#[derive(Clone, Copy, PartialEq)]
+enum Position {
+    Start(usize),
+    // 1 being the last parameter
+    End(usize),
+}
+
+impl Length {
+    // every length a value of the constructor can have
+    pub(crate) fn any(constr: &impl LengthedConstructor) -> Self {
+        Length::range(0..=max_length(constr))
+    }
+
+    pub(crate) fn exact(n: usize) -> Self {
+        Length {
+            lengths: n..=n,
+            prefix: n,
+            suffix: 0,
+        }
+    }
+
+    pub(crate) fn range(lengths: RangeInclusive<usize>) -> Self {
+        assert!(
+            lengths.start() <= lengths.end(),
+            "length range with its bounds swapped"
+        );
+        Length {
+            lengths,
+            prefix: 0,
+            suffix: 0,
+        }
+    }
+
+    // `params` parameters or more up to `max`, with the rest placed after the first `rest_at`
+    // of them
+    pub(crate) fn rest(rest_at: usize, params: Params, max: usize) -> Self {
+        if params == max {
+            return Length::exact(params);
+        }
+
+        Length {
+            lengths: params..=max,
+            prefix: rest_at,
+            suffix: params - rest_at,
+        }
+    }
+
+    // the lengths matched by a lengthed constructor with `params` parameters
+    pub(crate) fn of<C: Constructors>(constr: &Constructor<C>, params: Params) -> Option<Self> {
+        match constr {
+            Constructor::Lenghted(_) => Some(Length::exact(params)),
+            Constructor::LengthedRest { constr, rest_at } => {
+                Some(Length::rest(*rest_at, params, max_length(constr)))
+            }
+            Constructor::LengthRange {
+                constr,
+                lengths,
+                rest_at,
+            } => {
+                let end = (*lengths.end()).min(max_length(constr));
+                Some(Length {
+                    lengths: *lengths.start()..=end,
+                    prefix: *rest_at,
+                    suffix: params - rest_at,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn params(&self) -> Params {
+        self.prefix + self.suffix
+    }
+
+    fn single(&self) -> Option<usize> {
+        (self.lengths.start() == self.lengths.end()).then(|| *self.lengths.start())
+    }
+
+    fn position(&self, i: usize, n: Option<usize>) -> Position {
+        match n {
+            _ if i < self.prefix => Position::Start(i),
+            Some(n) => Position::Start(n - (self.params() - i)),
+            None => Position::End(self.params() - i),
+        }
+    }
+
+    // the shapes which both this branch and a pattern of `other` can be rearranged into, covering
+    // the same lengths as this branch
+    fn common_shapes(&self, other: &Length) -> Vec<Length> {
+        let (start, end) = (*self.lengths.start(), *self.lengths.end());
+        let prefix = self.prefix.max(other.prefix);
+        let suffix = self.suffix.max(other.suffix);
+
+        // lengths shorter than the fixed parameters would have their prefix and suffix overlap
+        let fixed = prefix + suffix;
+        let mut shapes: Vec<Length> = (start..fixed.min(end)).map(Length::exact).collect();
+        if fixed <= end {
+            let lengths = start.max(fixed)..=end;
+            shapes.push(Length {
+                lengths,
+                prefix,
+                suffix,
+            });
+        } else {
+            shapes.push(Length::exact(end));
+        }
+        shapes
+    }
+}
+
 // for some constructors like infinite we hold on to the wildcard variants so we can re-merge
 // them into any additional variants we create afterwards.
 //
 // TODO: this feels hacky and unecesarry D:
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "C: crate::SerializeConstructors",
+        deserialize = "C: crate::DeserializeConstructors<'de>"
+    ))
+)]
 pub struct WildcardKeeper<C: Constructors> {
     buf: Vec<(C::Wildcard, FlatPatterns<C>)>,
-    con: Option<Box<PatternTree<C>>>,
+    con: Option<Arc<Node<C>>>,
 }
 
 impl<C: Constructors> WildcardKeeper<C> {
     fn new() -> Self {
-        Self { buf: vec![], con: None }
+        Self {
+            buf: vec![],
+            con: None,
+        }
     }
 
     fn init(wc: C::Wildcard, con: &mut FlatPatterns<C>) -> Self {
         let mut keeper = Self::new();
         keeper.buf.push((wc, con.clone()));
-        keeper.con = Some(Box::new(con.drain_to_patterntree()));
+        keeper.con = Some(Arc::new(con.drain_to_patterntree()));
         keeper
     }
 
-    fn with_wildcard(&mut self, wc: C::Wildcard, mut con: FlatPatterns<C>) -> IsReachable {
-        self.buf.push((wc, con.clone()));
-
-        match &mut self.con {
-            Some(existing) => con.merge_with(existing),
+    fn with_wildcard(&mut self, wc: C::Wildcard, con: FlatPatterns<C>) -> MergeResult<C::Meta> {
+        let is_reachable = match &mut self.con {
+            Some(existing) => con.clone().merge_with(Arc::make_mut(existing))?,
             a @ None => {
-                *a = Some(Box::new(con.drain_to_patterntree()));
+                *a = Some(Arc::new(con.clone().drain_to_patterntree()));
                 IsReachable(true)
             }
-        }
+        };
+
+        self.buf.push((wc, con));
+        Ok(is_reachable)
     }
+}
 
-    fn with_branch(
-        &self,
-        branch_con: Option<&mut PatternTree<C>>,
-        mut src: FlatPatterns<C>,
-    ) -> (IsReachable, Option<PatternTree<C>>) {
-        match branch_con {
-            Some(econ) => {
-                let reachable_via_version = src.clone().merge_with(econ);
+// the inhabited tags of a sum type as seen by the tree. Non-exhaustive sum types get an additional
+// tag after their last one, which stands for all of their unknown variants.
+trait Tags {
+    fn tags(&self) -> Vec<u64>;
+    fn unknown_tag(&self) -> u64;
+    fn is_unknown(&self, tag: u64) -> bool;
+    fn params_of(&self, tag: u64) -> Params;
+}
 
-                match self.con.as_deref().cloned() {
-                    Some(PatternTree::None) => (IsReachable(false), None),
-                    Some(_) | None => (reachable_via_version, None),
-                }
-            }
-            None => {
-                let mut continuation = src.drain_to_patterntree();
-                let is_reachable = self.merge_into_version(&mut continuation);
-                (is_reachable, Some(continuation))
-            }
+impl<S: SumtypeConstructor> Tags for S {
+    fn tags(&self) -> Vec<u64> {
+        let mut tags = match self.is_empty() {
+            true => vec![],
+            false => (0..=self.max())
+                .filter(|&tag| self.is_inhabited(tag))
+                .collect(),
+        };
+        if self.is_non_exhaustive() {
+            tags.push(self.unknown_tag());
         }
+        tags
     }
 
-    // when inserting as a new version we need to first merge any previous wildcards
-    // into that version to make it up-to-date.
-    fn merge_into_version(&self, given_con: &mut PatternTree<C>) -> IsReachable {
-        if self.buf.is_empty() {
-            return IsReachable(true);
-        }
-
-        if matches!(self.con.as_deref(), Some(&PatternTree::None)) {
-            return IsReachable(false);
+    fn unknown_tag(&self) -> u64 {
+        if self.is_empty() {
+            0
+        } else {
+            self.max() + 1
         }
+    }
 
-        let any_previous_wc_con_contains_version_con = self
-            .buf
-            .iter()
-            .any(|(_, previous_wc)| previous_wc.clone().merge_with(given_con).0);
+    fn is_unknown(&self, tag: u64) -> bool {
+        self.is_non_exhaustive() && tag == self.unknown_tag()
+    }
 
-        IsReachable(!any_previous_wc_con_contains_version_con)
+    fn params_of(&self, tag: u64) -> Params {
+        if self.is_unknown(tag) {
+            0
+        } else {
+            self.params_for(tag)
+        }
     }
 }
 
-impl<C: Constructors> PatternTree<C> {
+impl<C: Constructors> Node<C> {
     fn is_end(&self) -> bool {
-        matches!(self, Self::None)
+        matches!(self, Self::End(_))
+    }
+
+    // a tree which nothing has been included into yet
+    fn is_uncovered(&self) -> bool {
+        matches!(
+            self,
+            Self::UnknownWildcard(WildcardKeeper { con: None, .. })
+        )
+    }
+
+    // rearranges the parameters of a lengthed branch from the shape of `from` into the larger
+    // shape of `to` by filling the new parameters with wildcards
+    fn reshape(&mut self, from: &Length, to: &Length) {
+        let n = to.params() - from.params();
+        if n != 0 {
+            self.on_continuation(from.prefix, &mut |con| {
+                take_mut::take(con, |con| Self::prefixed_by_wildcards(n, con))
+            });
+        }
+    }
+
+    // a tree which skips over `n` parameters before continuing with `con`
+    fn prefixed_by_wildcards(n: usize, con: Self) -> Self {
+        let mut tree = FlatPatterns::new()
+            .clone_to_padded(n)
+            .drain_to_patterntree();
+        tree.on_continuation(n, &mut |end| *end = con.clone());
+        tree
     }
 
     fn on_continuation(&mut self, mut params: usize, f: &mut impl FnMut(&mut Self)) {
         if params == 0 {
             f(self)
         } else {
-            use PatternTree::*;
+            use Node::*;
 
             params -= 1;
 
             match self {
                 Variant(type_, branches) => {
-                    branches.on_continuation(params, |tag| type_.params_for(*tag), f)
+                    branches.on_continuation(params, |tag| type_.params_of(*tag), f)
                 }
                 SignedInteger { branches, .. } => branches.on_continuation(params, |_| 0, f),
                 UnsignedInteger { branches, .. } => branches.on_continuation(params, |_| 0, f),
-                Lengthed(_, wc, branches) => {
-                    branches.on_continuation(params, |p| *p, f);
-
-                    if let Some(con) = &mut wc.con {
-                        con.on_continuation(params, f);
-                    }
+                Char(branches) => branches.on_continuation(params, |_| 0, f),
+                Float(branches) => branches.on_continuation(params, |_| 0, f),
+                Lengthed(_, branches) => branches.on_continuation(params, Length::params, f),
+                Constant(constr, con) => {
+                    Arc::make_mut(con).on_continuation(params + constr.len_requirement(), f)
                 }
-                Constant(constr, con) => con.on_continuation(params + constr.len_requirement(), f),
                 Infinite(wc, branches) => {
                     branches.on_continuation(params, |_| 0, f);
 
                     if let Some(con) = &mut wc.con {
-                        con.on_continuation(params, f);
+                        Arc::make_mut(con).on_continuation(params, f);
                     }
                 }
                 UnknownWildcard(keeper) => {
-                    if let Some(con) = keeper.con.as_deref_mut() {
+                    if let Some(con) = keeper.con.as_mut().map(Arc::make_mut) {
                         con.on_continuation(params, f)
                     }
                 }
-                None => panic!("FlatPatterns ended unexpectedly"),
+                End(_) => panic!("FlatPatterns ended unexpectedly"),
             }
         }
     }
@@ -193,6 +509,22 @@ impl<C: Constructors> PatternTree<C> {
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub struct IsReachable(pub bool);
 
+/// The reachability of a pattern and of the individual alternatives of its or-patterns
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Reachability<M> {
+    pub is_reachable: IsReachable,
+    /// The paths to the alternatives which are already covered by previous patterns or by the
+    /// alternatives before them.
+    ///
+    /// A path is the parameter indices leading to the or-pattern followed by the index of the
+    /// alternative. Alternatives nested within an already redundant alternative are left out.
+    pub redundant_alternatives: Vec<Vec<usize>>,
+    /// The metadata of each of the redundant alternatives, in the same order
+    pub redundant_meta: Vec<M>,
+    /// The earlier arms which together already cover the entire pattern if it's unreachable
+    pub covered_by: Vec<Arm>,
+}
+
 impl std::ops::BitOr for IsReachable {
     type Output = Self;
 
@@ -218,13 +550,273 @@ impl std::ops::BitAndAssign for IsReachable {
     }
 }
 
+/// A pattern which couldn't be included into a `PatternTree` because its constructors don't line
+/// up with the patterns that were included before it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeError<M> {
+    /// The parameter indices leading from the root of the pattern to the offending constructor
+    pub path: Vec<usize>,
+    pub kind: MergeErrorKind,
+    /// The metadata of the offending constructor's pattern
+    pub meta: M,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeErrorKind {
+    /// The constructor is of a different kind than the existing ones at this position
+    KindMismatch,
+    /// The constructor takes a different amount of parameters than the existing ones
+    ArityMismatch,
+    /// The integer range pattern has a different bitsize than the existing ones
+    BitsizeMismatch { expected: u8, found: u8 },
+    /// The variant belongs to a different sum type than the existing ones
+    SumTypeMismatch,
+    /// The range pattern has its bounds swapped, only one of them being NaN, integers outside of
+    /// its bitsize or a bitsize of 0 or above 128, or lengths starting after the maximum length or
+    /// before the end of their parameters
+    InvalidRange,
+}
+
+impl<M> fmt::Display for MergeError<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            MergeErrorKind::KindMismatch => write!(f, "mismatched kind of constructor")?,
+            MergeErrorKind::ArityMismatch => write!(f, "mismatched amount of parameters")?,
+            MergeErrorKind::BitsizeMismatch { expected, found } => write!(
+                f,
+                "mismatched bitsize of integer, expected {} but found {}",
+                expected, found
+            )?,
+            MergeErrorKind::SumTypeMismatch => write!(f, "mismatched sum type")?,
+            MergeErrorKind::InvalidRange => write!(f, "invalid range")?,
+        }
+
+        write!(f, " at {:?}", self.path)
+    }
+}
+
+impl<M: fmt::Debug> std::error::Error for MergeError<M> {}
+
+// a `MergeError` located by its index in the flattened pattern
+#[derive(Clone, Debug)]
+pub(crate) struct Mismatch<M> {
+    at: usize,
+    kind: MergeErrorKind,
+    meta: M,
+}
+
+pub(crate) type MergeResult<M> = Result<IsReachable, Mismatch<M>>;
+
+impl<C: Constructors> FlatPatterns<C> {
+    // the error for the constructor which was most recently popped
+    fn mismatch(&self, kind: MergeErrorKind) -> Mismatch<C::Meta> {
+        let at = self.consumed().saturating_sub(1);
+        Mismatch {
+            at,
+            kind,
+            meta: self.meta(at),
+        }
+    }
+
+    fn check_range(&self, params: usize, found: u8, expected: u8) -> Result<(), Mismatch<C::Meta>> {
+        if params != 0 {
+            Err(self.mismatch(MergeErrorKind::ArityMismatch))
+        } else if found != expected {
+            Err(self.mismatch(MergeErrorKind::BitsizeMismatch { expected, found }))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<C: Constructors> Default for PatternTree<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<C: Constructors> PatternTree<C> {
+    /// The tree of a match over any type which doesn't have any arms yet
+    ///
+    /// Matches whose first arm has a guard start out from this tree.
+    pub fn new() -> Self {
+        PatternTree {
+            root: Node::UnknownWildcard(WildcardKeeper::new()),
+            next_arm: 0,
+        }
+    }
+
+    /// The node at the first position of the patterns, to inspect the tree from
+    pub fn root(&self) -> &Node<C> {
+        &self.root
+    }
+
     pub fn from_pattern(p: &Pattern<C>) -> Self {
-        p.flatten().drain_to_patterntree()
+        Self::from_arm(0, p)
+    }
+
+    /// The tree of a match over a sum type which doesn't have any arms yet
+    ///
+    /// Such a match is only exhaustive if the sum type is uninhabited.
+    pub fn empty(type_: C::SumType) -> Self {
+        PatternTree {
+            root: Node::Variant(type_, vec![]),
+            next_arm: 0,
+        }
+    }
+
+    /// Creates a tree whose leaves are marked as covered by the given arm
+    pub fn from_arm(arm: Arm, p: &Pattern<C>) -> Self {
+        let mut tree = Self::new();
+        tree.include_arm(arm, p);
+        tree
     }
 
     pub fn include_pattern(&mut self, p: &Pattern<C>) -> IsReachable {
-        p.flatten().merge_with(self)
+        self.include_alternatives(p).is_reachable
+    }
+
+    /// Like `include_pattern` but reports a pattern which doesn't fit the constructors of the
+    /// previously included patterns instead of panicking.
+    ///
+    /// The tree is left untouched if an error is returned.
+    pub fn try_include_pattern(
+        &mut self,
+        p: &Pattern<C>,
+    ) -> Result<IsReachable, MergeError<C::Meta>> {
+        self.try_include_alternatives(p)
+            .map(|reachability| reachability.is_reachable)
+    }
+
+    /// Like `include_pattern` but also reports which alternatives of the or-patterns are redundant
+    ///
+    /// Each combination of the alternatives is included on its own, which takes time exponential
+    /// in the amount of or-patterns placed side by side.
+    pub fn include_alternatives(&mut self, p: &Pattern<C>) -> Reachability<C::Meta> {
+        self.include_arm(self.next_arm, p)
+    }
+
+    pub fn try_include_alternatives(
+        &mut self,
+        p: &Pattern<C>,
+    ) -> Result<Reachability<C::Meta>, MergeError<C::Meta>> {
+        self.try_include_arm(self.next_arm, p)
+    }
+
+    /// Includes the pattern of a match arm, marking everything it newly covers with `arm`.
+    ///
+    /// The other include methods use the arm after the last one included, whether that arm
+    /// turned out to be reachable or not.
+    pub fn include_arm(&mut self, arm: Arm, p: &Pattern<C>) -> Reachability<C::Meta> {
+        self.try_include_arm(arm, p)
+            .expect("pattern does not fit the existing pattern tree")
+    }
+
+    pub fn try_include_arm(
+        &mut self,
+        arm: Arm,
+        p: &Pattern<C>,
+    ) -> Result<Reachability<C::Meta>, MergeError<C::Meta>> {
+        if let Some(path) = p.invalid_range() {
+            let meta = p
+                .at(&path)
+                .expect("range is not in the pattern")
+                .meta
+                .clone();
+            let kind = MergeErrorKind::InvalidRange;
+            return Err(MergeError { path, kind, meta });
+        }
+
+        // the nodes are shared with the copy until they're changed, so only the ones the pattern
+        // is merged into are copied. The tree is left untouched if the pattern doesn't fit.
+        let mut tree = self.root.clone();
+        let mut is_reachable = IsReachable(false);
+        let mut picked = vec![];
+        let mut reached = vec![];
+
+        for expansion in p.expand_alternatives() {
+            let pattern = &expansion.pattern;
+            let flat = pattern.flatten().with_arm(arm);
+
+            // opaque constants could be equal to anything, so they can't cover any values
+            let mut checked = match expansion.opaque {
+                true => Some(tree.clone()),
+                false => None,
+            };
+            let dst = checked.as_mut().unwrap_or(&mut tree);
+
+            let expansion_is_reachable =
+                flat.merge_with(dst)
+                    .map_err(|Mismatch { at, kind, meta }| {
+                        let path = p.original_path(&pattern.path_to(at), &expansion.picked);
+                        MergeError { path, kind, meta }
+                    })?;
+
+            if expansion_is_reachable.0 {
+                reached.extend(expansion.picked.iter().cloned());
+            }
+            is_reachable |= expansion_is_reachable;
+            picked.extend(expansion.picked);
+        }
+
+        picked.sort();
+        picked.dedup();
+
+        let mut redundant_alternatives: Vec<Vec<usize>> = vec![];
+        for path in picked {
+            let within_redundant = redundant_alternatives.iter().any(|r| path.starts_with(r));
+            if !within_redundant && !reached.contains(&path) {
+                redundant_alternatives.push(path);
+            }
+        }
+
+        let redundant_meta = redundant_alternatives
+            .iter()
+            .map(|path| {
+                p.at(path)
+                    .expect("alternative is not in the pattern")
+                    .meta
+                    .clone()
+            })
+            .collect();
+
+        let covered_by = if is_reachable.0 {
+            vec![]
+        } else {
+            self.arms_matching(p)
+        };
+
+        self.root = tree;
+        self.next_arm = self.next_arm.max(arm + 1);
+        Ok(Reachability {
+            is_reachable,
+            redundant_alternatives,
+            redundant_meta,
+            covered_by,
+        })
+    }
+
+    /// Checks whether the pattern of an arm with a guard is reachable.
+    ///
+    /// Since the guard might fail, the pattern is not recorded as covering anything. It still
+    /// uses up an arm though, so the arms of the patterns after it line up with the match.
+    pub fn check_guarded_pattern(&mut self, p: &Pattern<C>) -> IsReachable {
+        self.check_guarded_alternatives(p).is_reachable
+    }
+
+    pub fn check_guarded_alternatives(&mut self, p: &Pattern<C>) -> Reachability<C::Meta> {
+        self.try_check_guarded_alternatives(p)
+            .expect("pattern does not fit the existing pattern tree")
+    }
+
+    pub fn try_check_guarded_alternatives(
+        &mut self,
+        p: &Pattern<C>,
+    ) -> Result<Reachability<C::Meta>, MergeError<C::Meta>> {
+        let mut tree = self.clone();
+        let reachability = tree.try_include_alternatives(p)?;
+        self.next_arm = tree.next_arm;
+        Ok(reachability)
     }
 
     pub fn is_exhaustive(&self) -> bool {
@@ -234,28 +826,94 @@ impl<C: Constructors> PatternTree<C> {
     }
 }
 
+impl<C: Constructors> Pattern<C> {
+    // the path to the first range pattern which doesn't make up a range of values
+    fn invalid_range(&self) -> Option<Vec<usize>> {
+        if !self.constr.is_valid_range(self.params.len()) {
+            return Some(vec![]);
+        }
+
+        self.params.iter().enumerate().find_map(|(i, param)| {
+            let mut path = param.invalid_range()?;
+            path.insert(0, i);
+            Some(path)
+        })
+    }
+}
+
 impl<C: Constructors> Constructor<C> {
-    fn into_patterntree(self, params: usize, src: &mut FlatPatterns<C>) -> PatternTree<C> {
+    // whether the constructor isn't a range, or is one whose bounds the tree can work with
+    fn is_valid_range(&self, params: usize) -> bool {
+        match self {
+            // the bitsize is checked first since the bounds of an invalid one can't be computed
+            &Self::SignedInteger { ref range, bitsize } => {
+                (1..=128).contains(&bitsize)
+                    && signed_min(bitsize as u32) <= *range.start()
+                    && range.start() <= range.end()
+                    && *range.end() <= signed_max(bitsize as u32)
+            }
+            &Self::UnsignedInteger { ref range, bitsize } => {
+                (1..=128).contains(&bitsize)
+                    && range.start() <= range.end()
+                    && *range.end() <= unsigned_max(bitsize as u32)
+            }
+            Self::Char { range } => range.start() <= range.end(),
+            Self::Float { range } => match (range.start().is_nan(), range.end().is_nan()) {
+                (false, false) => range.start() <= range.end(),
+                (start, end) => start && end,
+            },
+            Self::LengthRange {
+                constr,
+                lengths,
+                rest_at,
+            } => {
+                lengths.start() <= lengths.end()
+                    && *lengths.start() <= max_length(constr)
+                    && *rest_at <= params
+                    && params <= *lengths.start()
+            }
+            _ => true,
+        }
+    }
+
+    fn into_patterntree(self, params: usize, src: &mut FlatPatterns<C>) -> Node<C> {
         match self {
-            Self::Variant { type_, tag } => PatternTree::Variant(type_, src.drain_to_branches(tag)),
-            Self::SignedInteger { range, bitsize } => PatternTree::SignedInteger {
+            Self::Variant { type_, tag } => Node::Variant(type_, src.drain_to_branches(tag)),
+            Self::SignedInteger { range, bitsize } => Node::SignedInteger {
                 bitsize,
                 branches: src.drain_to_branches(range),
             },
-            Self::UnsignedInteger { range, bitsize } => PatternTree::UnsignedInteger {
+            Self::UnsignedInteger { range, bitsize } => Node::UnsignedInteger {
                 bitsize,
                 branches: src.drain_to_branches(range),
             },
-            Self::Lenghted(constr) => {
-                PatternTree::Lengthed(constr, WildcardKeeper::new(), src.drain_to_branches(params))
+            Self::Char { range } => Node::Char(src.drain_to_branches(range)),
+            Self::Float { range } => Node::Float(src.drain_to_branches(OrderedFloat::range(range))),
+            Self::Lenghted(ref constr)
+            | Self::LengthedRest { ref constr, .. }
+            | Self::LengthRange { ref constr, .. } => {
+                let length = Length::of(&self, params).unwrap();
+                Node::Lengthed(constr.clone(), src.drain_to_branches(length))
             }
-            Self::Constant(constr) => {
-                PatternTree::Constant(constr, Box::new(src.drain_to_patterntree()))
-            }
-            Self::Infinite(constr) => {
-                PatternTree::Infinite(WildcardKeeper::new(), src.drain_to_branches(constr))
+            Self::Constant(constr) => Node::Constant(constr, Arc::new(src.drain_to_patterntree())),
+            Self::Infinite(constr) => Node::Infinite(
+                WildcardKeeper::new(),
+                src.drain_to_branches(InfiniteValues::Literal(constr)),
+            ),
+            Self::InfiniteAffix {
+                prefix: None,
+                suffix: None,
+            } => {
+                let wc = WildcardKeeper::init(C::Wildcard::default(), src);
+                Node::Infinite(wc, vec![])
             }
-            Self::Wildcard(wc) => PatternTree::UnknownWildcard(WildcardKeeper::init(wc, src)),
+            Self::InfiniteAffix { prefix, suffix } => Node::Infinite(
+                WildcardKeeper::new(),
+                src.drain_to_branches(InfiniteValues::Affixed { prefix, suffix }),
+            ),
+            Self::Wildcard(wc) => Node::UnknownWildcard(WildcardKeeper::init(wc, src)),
+            Self::Or => unreachable!("or-patterns are expanded before being flattened"),
+            Self::Opaque(_) => unreachable!("opaque constants are replaced before being flattened"),
         }
     }
 }
@@ -269,27 +927,27 @@ impl<C: Constructors> FlatPatterns<C> {
         clone
     }
 
-    fn merge_with(self, tree: &mut PatternTree<C>) -> IsReachable {
+    fn merge_with(self, tree: &mut Node<C>) -> MergeResult<C::Meta> {
         Merge::new(self, tree).run()
     }
 
     // TODO: we can optimize this a lot by working with 'self' instead of '&mut self'
-    pub fn drain_to_patterntree(&mut self) -> PatternTree<C> {
+    pub(crate) fn drain_to_patterntree(&mut self) -> Node<C> {
         match self.pop_front() {
             Some((constr, params)) => constr.into_patterntree(params, self),
-            None => PatternTree::None,
+            None => Node::End(vec![self.arm()]),
         }
     }
 
     fn drain_to_branches<A>(&mut self, data: A) -> Vec<Branch<C, A>> {
         vec![Branch {
             data,
-            con: self.drain_to_patterntree(),
+            con: Arc::new(self.drain_to_patterntree()),
         }]
     }
 }
 
-impl<C: Constructors> PatternTree<C> {
+impl<C: Constructors> Node<C> {
     fn fmt_cont(&self) -> String {
         if self.is_end() {
             return String::new();
@@ -316,9 +974,9 @@ fn fmt_branches<C: Constructors, A: std::fmt::Debug>(
 fn fmt_wildcard<C: Constructors>(f: &mut fmt::Formatter, wc: &WildcardKeeper<C>) -> fmt::Result {
     let name = wc
         .buf
-        .get(0)
+        .first()
         .map(|(card, _)| card.clone())
-        .unwrap_or_else(C::Wildcard::default);
+        .unwrap_or_default();
     if let Some(con) = wc.con.as_deref() {
         writeln!(f, "{:?}{}", name, con.fmt_cont())
     } else {
@@ -327,34 +985,34 @@ fn fmt_wildcard<C: Constructors>(f: &mut fmt::Formatter, wc: &WildcardKeeper<C>)
 }
 
 impl<C: Constructors> fmt::Display for PatternTree<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.root.fmt(f)
+    }
+}
+
+impl<C: Constructors> fmt::Display for Node<C> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            PatternTree::SignedInteger { branches, .. } => fmt_branches(f, branches),
-            PatternTree::UnsignedInteger { branches, .. } => fmt_branches(f, branches),
-            PatternTree::Variant(constr, branches) => {
+            Node::SignedInteger { branches, .. } => fmt_branches(f, branches),
+            Node::UnsignedInteger { branches, .. } => fmt_branches(f, branches),
+            Node::Char(branches) => fmt_branches(f, branches),
+            Node::Float(branches) => fmt_branches(f, branches),
+            Node::Variant(constr, branches) => {
                 branches.iter().try_for_each(|Branch { data: tag, con }| {
                     writeln!(f, "{:?}[{}]{}", constr, tag, con.fmt_cont())
                 })
             }
-            PatternTree::Infinite(wc, branches) => {
+            Node::Infinite(wc, branches) => {
                 fmt_branches(f, branches).and_then(|_| fmt_wildcard(f, wc))
             }
-            PatternTree::Lengthed(constr, wc, branches) => branches
-                .iter()
-                .try_for_each(|Branch { con, .. }| writeln!(f, "{:?}{}", constr, con.fmt_cont()))
-                .and_then(|_| fmt_wildcard(f, wc)),
-            PatternTree::Constant(constr, con) => write!(f, "{:?}{}", constr, con.fmt_cont()),
-            PatternTree::UnknownWildcard(keeper) => writeln!(
-                f,
-                "{:?}{}",
-                &keeper.buf[0].0,
-                keeper
-                    .con
-                    .as_deref()
-                    .unwrap_or(&PatternTree::None)
-                    .fmt_cont()
-            ),
-            PatternTree::None => Ok(()),
+            Node::Lengthed(constr, branches) => {
+                branches.iter().try_for_each(|Branch { data, con }| {
+                    writeln!(f, "{:?}{:?}{}", constr, data, con.fmt_cont())
+                })
+            }
+            Node::Constant(constr, con) => write!(f, "{:?}{}", constr, con.fmt_cont()),
+            Node::UnknownWildcard(keeper) => fmt_wildcard(f, keeper),
+            Node::End(_) => Ok(()),
         }
     }
 }
@@ -0,0 +1,153 @@
+use super::merge::RangeBound;
+use super::{Arm, Branch, Node, PatternTree, RangeBranch, WildcardKeeper};
+use crate::Constructors;
+use std::sync::Arc;
+
+impl<C: Constructors> PatternTree<C> {
+    /// Removes an arm from the tree, leaving it as if the arm's pattern was never included
+    ///
+    /// The values the arm was the first to cover are handed to the next arm covering them, or
+    /// left uncovered if there is none, so `arms` afterwards tells which of the later arms became
+    /// reachable. Ranges the arm split apart are joined back together.
+    ///
+    /// The arms of the patterns included afterwards keep counting on from the removed ones.
+    pub fn remove_arm(&mut self, arm: Arm) {
+        // the sum type of a tree made by `PatternTree::empty` is kept around
+        if self.root.without_arm(arm) && !matches!(self.root, Node::Variant(..)) {
+            self.root = Node::UnknownWildcard(WildcardKeeper::new());
+        }
+    }
+}
+
+impl<C: Constructors> Node<C> {
+    // removes the arm from the tree, returning whether nothing is covered anymore
+    fn without_arm(&mut self, arm: Arm) -> bool {
+        match self {
+            Node::SignedInteger { branches, .. } => ranges_without_arm(branches, arm),
+            Node::UnsignedInteger { branches, .. } => ranges_without_arm(branches, arm),
+            Node::Char(branches) => ranges_without_arm(branches, arm),
+            Node::Float(branches) => ranges_without_arm(branches, arm),
+            Node::Variant(_, branches) => branches_without_arm(branches, arm),
+            Node::Lengthed(_, branches) => branches_without_arm(branches, arm),
+            Node::Constant(_, con) => Arc::make_mut(con).without_arm(arm),
+            Node::Infinite(wc, branches) => {
+                let uncovered = wc.without_arm(arm);
+                branches_without_arm(branches, arm) && uncovered
+            }
+            Node::UnknownWildcard(wc) => wc.without_arm(arm),
+            Node::End(arms) => {
+                arms.retain(|&a| a != arm);
+                arms.is_empty()
+            }
+        }
+    }
+
+    // whether the trees cover the same values with the same arms in the same way
+    fn equivalent(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Node::SignedInteger { bitsize, branches },
+                Node::SignedInteger {
+                    bitsize: bs,
+                    branches: other,
+                },
+            ) => bitsize == bs && equivalent_branches(branches, other),
+            (
+                Node::UnsignedInteger { bitsize, branches },
+                Node::UnsignedInteger {
+                    bitsize: bs,
+                    branches: other,
+                },
+            ) => bitsize == bs && equivalent_branches(branches, other),
+            (Node::Char(branches), Node::Char(other)) => equivalent_branches(branches, other),
+            (Node::Float(branches), Node::Float(other)) => equivalent_branches(branches, other),
+            (Node::Variant(type_, branches), Node::Variant(t, other)) => {
+                type_ == t && equivalent_branches(branches, other)
+            }
+            (Node::Lengthed(constr, branches), Node::Lengthed(c, other)) => {
+                constr == c && equivalent_branches(branches, other)
+            }
+            (Node::Constant(constr, con), Node::Constant(c, other)) => {
+                constr == c && con.equivalent(other)
+            }
+            (Node::Infinite(wc, branches), Node::Infinite(w, other)) => {
+                wc.equivalent(w) && equivalent_branches(branches, other)
+            }
+            (Node::UnknownWildcard(wc), Node::UnknownWildcard(w)) => wc.equivalent(w),
+            (Node::End(arms), Node::End(other)) => arms == other,
+            _ => false,
+        }
+    }
+}
+
+impl<C: Constructors> WildcardKeeper<C> {
+    fn without_arm(&mut self, arm: Arm) -> bool {
+        self.buf.retain(|(_, flat)| flat.arm() != arm);
+        if self
+            .con
+            .as_mut()
+            .is_some_and(|con| Arc::make_mut(con).without_arm(arm))
+        {
+            self.con = None;
+        }
+        self.con.is_none()
+    }
+
+    fn equivalent(&self, other: &Self) -> bool {
+        match (self.con.as_deref(), other.con.as_deref()) {
+            (Some(con), Some(other)) => con.equivalent(other),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+fn branches_without_arm<C: Constructors, A>(branches: &mut Vec<Branch<C, A>>, arm: Arm) -> bool {
+    branches.retain_mut(|branch| !Arc::make_mut(&mut branch.con).without_arm(arm));
+    branches.is_empty()
+}
+
+// also joins the neighbouring ranges which the arm was the only one to tell apart
+fn ranges_without_arm<C: Constructors, N: RangeBound>(
+    branches: &mut Vec<RangeBranch<C, N>>,
+    arm: Arm,
+) -> bool {
+    if branches_without_arm(branches, arm) {
+        return true;
+    }
+
+    branches.sort_unstable_by_key(|branch| *branch.data.start());
+    let mut joined: Vec<RangeBranch<C, N>> = Vec::with_capacity(branches.len());
+    for branch in branches.drain(..) {
+        match joined.last_mut() {
+            Some(last) if joinable(last, &branch) => {
+                last.data = *last.data.start()..=*branch.data.end();
+            }
+            _ => joined.push(branch),
+        }
+    }
+    *branches = joined;
+
+    false
+}
+
+// whether the range of `b` directly follows the one of `a` and they're covered the same way
+fn joinable<C: Constructors, N: RangeBound>(a: &RangeBranch<C, N>, b: &RangeBranch<C, N>) -> bool {
+    let (end, start) = (*a.data.end(), *b.data.start());
+    end < start
+        && end.incr() == start
+        && N::contiguous(*a.data.start()..=*b.data.end()).len() == 1
+        && a.con.equivalent(&b.con)
+}
+
+fn equivalent_branches<C: Constructors, A: PartialEq>(
+    branches: &[Branch<C, A>],
+    other: &[Branch<C, A>],
+) -> bool {
+    branches.len() == other.len()
+        && branches.iter().all(|branch| {
+            other
+                .iter()
+                .any(|o| o.data == branch.data && o.con.equivalent(&branch.con))
+        })
+}